@@ -9,11 +9,14 @@ use super::pooled_cache::PooledCacheIndex;
 use super::{AssetData, ASSET_STATE_LOADED, AssetTypes, get_slot_mut, get_slot_index, Sounds, asset_cache};
 use crate::generational_array::{GenerationalArray, GenerationalIndex};
 
-const MAX_SOUNDS: usize = 64;
+const SOUND_CHUNK_SIZE: usize = 64;
+/// A warning is logged whenever the number of playing sounds goes past this
+const SOUND_SOFT_LIMIT: usize = 256;
 pub type SoundHandle = GenerationalIndex;
 // mod sound_list;
 lazy_static::lazy_static! {
-    pub static ref SOUNDS: Mutex<GenerationalArray<PlayingSound, MAX_SOUNDS>> = Mutex::new(GenerationalArray::new());
+    pub static ref SOUNDS: Mutex<GenerationalArray<PlayingSound, SOUND_CHUNK_SIZE>> =
+        Mutex::new(GenerationalArray::with_soft_limit(SOUND_SOFT_LIMIT, |limit| crate::logger::warn!("More than {} sounds are playing", limit)));
 }
 
 #[cfg(target_os = "windows")]
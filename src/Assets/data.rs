@@ -0,0 +1,36 @@
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+use crate::logger::{PanicLogEntry, info};
+use crate::job_system::{RawDataPointer, JobType, ThreadSafeJobQueue};
+use super::{AssetTypes, AssetSlot, AssetData};
+use super::{ASSET_STATE_LOADED, get_slot_mut, DataFiles};
+
+/// Returns the contents of a data file if it has finished loading
+/// Data files should be added to a scene's asset manifest so they are available by the time it loads
+pub fn request_data<'a>(queue: &ThreadSafeJobQueue, data: DataFiles) -> Option<&'a str> {
+    let slot = get_slot_mut(AssetTypes::Data(data));
+
+    let lock = queue.lock().log_and_panic();
+    let mut queue = lock.borrow_mut();
+    if super::send_job_if_unloaded(&mut queue, slot, JobType::LoadData(slot.path)) {
+        return None;
+    }
+
+    if slot.state.load(Ordering::Acquire) == ASSET_STATE_LOADED && let AssetData::Data(data) = &slot.data {
+        slot.last_request = Instant::now();
+        return Some(data);
+    }
+    None
+}
+
+pub fn load_data_async(path: &'static str, slot: RawDataPointer) {
+    info!("Loading data file asynchronously {:?}", path);
+
+    let path = std::fs::canonicalize(path).expect("invalid data file path");
+    let data = std::fs::read_to_string(path).log_and_panic();
+
+    let asset_slot = slot.get_inner::<AssetSlot>();
+    asset_slot.size = data.len();
+    asset_slot.data = AssetData::Data(data);
+    asset_slot.state.swap(ASSET_STATE_LOADED, Ordering::AcqRel);
+}
@@ -0,0 +1,67 @@
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+use crate::logger::PanicLogEntry;
+use crate::job_system::{JobType, ThreadSafeJobQueue};
+use super::{AssetTypes, Images, Fonts, Sounds, DataFiles, get_slot_mut, ASSET_STATE_LOADED};
+
+/// List of assets a scene needs before it can be loaded
+#[derive(Default)]
+pub struct AssetManifest {
+    assets: Vec<AssetTypes>,
+}
+impl AssetManifest {
+    pub fn new() -> AssetManifest {
+        AssetManifest { assets: vec!() }
+    }
+
+    pub fn image(mut self, image: Images) -> AssetManifest {
+        self.assets.push(AssetTypes::Image(image));
+        self
+    }
+    pub fn font(mut self, font: Fonts) -> AssetManifest {
+        self.assets.push(AssetTypes::Font(font));
+        self
+    }
+    pub fn sound(mut self, sound: Sounds) -> AssetManifest {
+        self.assets.push(AssetTypes::Sound(sound));
+        self
+    }
+    pub fn data(mut self, data: DataFiles) -> AssetManifest {
+        self.assets.push(AssetTypes::Data(data));
+        self
+    }
+
+    /// Queues every asset that isn't already loaded or loading on the job system
+    pub fn request(&self, queue: &ThreadSafeJobQueue) {
+        let lock = queue.lock().log_and_panic();
+        let mut queue = lock.borrow_mut();
+        for asset in &self.assets {
+            let slot = get_slot_mut(*asset);
+            let job = match asset {
+                AssetTypes::Image(_) => JobType::LoadImage(slot.path),
+                AssetTypes::Font(_) => JobType::LoadFont(slot.path),
+                AssetTypes::Sound(_) => JobType::LoadSound(slot.path),
+                AssetTypes::Data(_) => JobType::LoadData(slot.path),
+            };
+
+            // Mark as requested so the asset isn't evicted before the scene gets to use it
+            slot.last_request = Instant::now();
+            super::send_job_if_unloaded(&mut queue, slot, job);
+        }
+    }
+
+    /// Fraction of the assets that have finished loading
+    pub fn progress(&self) -> f32 {
+        if self.assets.is_empty() { return 1.; }
+
+        let loaded = self.assets.iter().filter(|a| {
+            let slot = get_slot_mut(**a);
+            slot.state.load(Ordering::Acquire) == ASSET_STATE_LOADED
+        }).count();
+        loaded as f32 / self.assets.len() as f32
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.progress() >= 1.
+    }
+}
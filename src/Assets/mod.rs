@@ -8,12 +8,16 @@ use crate::graphics::{Texture, Font};
 mod image;
 mod font;
 mod sound;
+mod data;
+mod manifest;
 mod pooled_cache;
 pub use self::image::{request_image, load_image_async};
 pub use self::font::{request_font, load_font_async};
+pub use self::data::{request_data, load_data_async};
+pub use self::manifest::AssetManifest;
 pub use self::sound::{start_audio_engine, Sound, PlayingSound, SoundStatus, load_sound_async, SoundHandle};
 
-#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, serde::Deserialize)]
 pub enum Images {
     Testing,
     Slime,
@@ -30,6 +34,11 @@ pub enum Sounds {
     Shoot,
 }
 
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum DataFiles {
+    Levels,
+}
+
 const ASSET_STATE_UNLOADED: u8 = 0;
 const ASSET_STATE_PENDING: u8 = 1;
 const ASSET_STATE_LOADED: u8 = 2;
@@ -39,12 +48,14 @@ pub enum AssetTypes {
     Image(Images),
     Font(Fonts),
     Sound(Sounds),
+    Data(DataFiles),
 }
 
 pub enum AssetData {
     Image(Texture),
     Font(Font),
     Sound(Sound),
+    Data(String),
     RawImage(glium::texture::RawImage2d<'static, u8>),
     None,
 }
@@ -91,6 +102,7 @@ pub fn initialize_asset_cache() {
     cache.insert(AssetTypes::Sound(Sounds::Piano), AssetSlot::new("./resources/piano.wav"));
     cache.insert(AssetTypes::Sound(Sounds::Shoot), AssetSlot::new("./resources/shoot.wav"));
     cache.insert(AssetTypes::Image(Images::Slime), AssetSlot::new("./resources/slime.png"));
+    cache.insert(AssetTypes::Data(DataFiles::Levels), AssetSlot::new("./resources/levels.ron"));
 }
 
 pub fn get_slot_mut(t: AssetTypes) -> &'static mut AssetSlot {
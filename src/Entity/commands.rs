@@ -0,0 +1,77 @@
+#![allow(dead_code)]
+use super::{EntityBehavior, EntityHandle, EntityManager, EntityCreationOptions, SceneId, PERSISTENT_SCENE};
+use crate::V2;
+
+pub(super) enum EntityCommand {
+    Create(Box<dyn EntityBehavior>, V2, EntityCreationOptions),
+    Destroy(EntityHandle),
+    Apply(Box<dyn FnOnce(&mut EntityManager)>),
+}
+
+/// Entity changes queued while entities are updating
+/// Commands are applied in the order they were queued by `EntityManager::apply_commands`,
+/// entities created by them belong to the scene that was current when they were queued
+/// Commands queued by persistent entities go to the scene that is on top when they are applied
+pub struct EntityCommands {
+    commands: Vec<(SceneId, EntityCommand)>,
+    pub(super) scene: SceneId,
+}
+impl EntityCommands {
+    pub fn new() -> EntityCommands {
+        EntityCommands { commands: vec!(), scene: 0 }
+    }
+
+    pub fn create_at(&mut self, behavior: impl EntityBehavior + 'static, position: V2) {
+        self.create_options_at(behavior, position, EntityCreationOptions::None);
+    }
+    pub fn create_options_at(&mut self, behavior: impl EntityBehavior + 'static, position: V2, options: EntityCreationOptions) {
+        self.push(EntityCommand::Create(Box::new(behavior), position, options));
+    }
+    pub fn spawn_prefab(&mut self, name: &str, position: V2) {
+        let name = String::from(name);
+        self.push(EntityCommand::Apply(Box::new(move |e| { e.spawn_prefab(&name, position); })));
+    }
+    pub fn spawn_prefab_with<T: 'static>(&mut self, name: &str, position: V2, configure: impl FnOnce(&mut T) + 'static) {
+        let name = String::from(name);
+        self.push(EntityCommand::Apply(Box::new(move |e| { e.spawn_prefab_with(&name, position, configure); })));
+    }
+    pub fn destroy(&mut self, handle: EntityHandle) {
+        self.push(EntityCommand::Destroy(handle));
+    }
+    pub fn add_component<T: 'static>(&mut self, handle: EntityHandle, component: T) {
+        self.push(EntityCommand::Apply(Box::new(move |e| e.add_component(handle, component))));
+    }
+    pub fn remove_component<T: 'static>(&mut self, handle: EntityHandle) {
+        self.push(EntityCommand::Apply(Box::new(move |e| { e.remove_component::<T>(&handle); })));
+    }
+
+    fn push(&mut self, command: EntityCommand) {
+        self.commands.push((self.scene, command));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub(super) fn apply(commands: Vec<(SceneId, EntityCommand)>, entities: &mut EntityManager, top: SceneId) {
+        for (scene, c) in commands {
+            let scene = if scene == PERSISTENT_SCENE { top } else { scene };
+            if entities.scene_unloaded(scene) { continue; }
+            entities.set_current_scene(scene);
+            match c {
+                EntityCommand::Create(behavior, position, options) => { entities.create_boxed(behavior, position, options, None); },
+                EntityCommand::Destroy(handle) => {
+                    if let Some(e) = entities.get_mut(&handle) { e.destroy(); }
+                },
+                EntityCommand::Apply(f) => f(entities),
+            }
+        }
+    }
+
+    pub(super) fn take(&mut self) -> Vec<(SceneId, EntityCommand)> {
+        std::mem::take(&mut self.commands)
+    }
+}
+impl Default for EntityCommands {
+    fn default() -> Self { Self::new() }
+}
@@ -0,0 +1,227 @@
+#![allow(dead_code)]
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use super::EntityHandle;
+
+trait Column: Any {
+    fn remove(&mut self, handle: &EntityHandle);
+    fn handles(&self) -> Vec<EntityHandle>;
+    fn len(&self) -> usize;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+struct TypedColumn<T: 'static> {
+    values: HashMap<EntityHandle, T>,
+}
+impl<T: 'static> Column for TypedColumn<T> {
+    fn remove(&mut self, handle: &EntityHandle) { self.values.remove(handle); }
+    fn handles(&self) -> Vec<EntityHandle> { self.values.keys().copied().collect() }
+    fn len(&self) -> usize { self.values.len() }
+    fn as_any(&self) -> &dyn Any { self }
+    fn as_any_mut(&mut self) -> &mut dyn Any { self }
+}
+
+/// Optional data attached to entities, stored in one column per component type
+pub struct Components {
+    columns: HashMap<TypeId, Box<dyn Column>>,
+}
+impl Components {
+    pub fn new() -> Components {
+        Components { columns: HashMap::new() }
+    }
+
+    fn column<T: 'static>(&self) -> Option<&HashMap<EntityHandle, T>> {
+        let column = self.columns.get(&TypeId::of::<T>())?;
+        Some(&column.as_any().downcast_ref::<TypedColumn<T>>().unwrap().values)
+    }
+    fn column_mut<T: 'static>(&mut self) -> Option<&mut HashMap<EntityHandle, T>> {
+        let column = self.columns.get_mut(&TypeId::of::<T>())?;
+        Some(&mut column.as_any_mut().downcast_mut::<TypedColumn<T>>().unwrap().values)
+    }
+
+    pub fn insert<T: 'static>(&mut self, handle: EntityHandle, value: T) {
+        let column = self.columns.entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(TypedColumn::<T> { values: HashMap::new() }));
+        column.as_any_mut().downcast_mut::<TypedColumn<T>>().unwrap().values.insert(handle, value);
+    }
+    pub fn remove<T: 'static>(&mut self, handle: &EntityHandle) -> Option<T> {
+        self.column_mut::<T>()?.remove(handle)
+    }
+    pub fn get<T: 'static>(&self, handle: &EntityHandle) -> Option<&T> {
+        self.column::<T>()?.get(handle)
+    }
+    pub fn get_mut<T: 'static>(&mut self, handle: &EntityHandle) -> Option<&mut T> {
+        self.column_mut::<T>()?.get_mut(handle)
+    }
+
+    /// Removes every component attached to the entity
+    pub fn remove_entity(&mut self, handle: &EntityHandle) {
+        for c in self.columns.values_mut() {
+            c.remove(handle);
+        }
+    }
+
+    pub fn query<Q: Query>(&mut self) -> QueryIter<'_, Q> {
+        let types = Q::types();
+        for (i, (t, mutable)) in types.iter().enumerate() {
+            let aliased = types.iter().enumerate().any(|(j, (other, _))| i != j && other == t);
+            assert!(!(*mutable && aliased), "Query borrows a mutable component more than once");
+        }
+
+        // Only entities in the smallest column can match every component
+        let handles = types.iter()
+            .filter_map(|(t, _)| self.columns.get(t))
+            .min_by_key(|c| c.len())
+            .map(|c| c.handles())
+            .unwrap_or_default();
+
+        // Columns are read once, they can't be changed while the query borrows the components
+        let columns = Q::columns(self);
+        QueryIter { columns, handles, current: 0, phantom: PhantomData }
+    }
+}
+impl Default for Components {
+    fn default() -> Self { Self::new() }
+}
+
+/// A single element of a `Query`, either `&T`, `&mut T` or `EntityHandle`
+pub trait QueryParam {
+    type Item<'a>;
+    /// Pointers to the component of every entity, taken once per query so rows never borrow the column again
+    type Column;
+    /// Component type and whether it is borrowed mutably
+    fn component() -> Option<(TypeId, bool)>;
+    /// Returns `None` if no entity has the component
+    fn column(components: &mut Components) -> Option<Self::Column>;
+    /// # Safety
+    /// The components must still be alive and each entity may only be fetched once
+    unsafe fn fetch<'a>(column: &Self::Column, handle: &EntityHandle) -> Option<Self::Item<'a>>;
+}
+impl<T: 'static> QueryParam for &T {
+    type Item<'a> = &'a T;
+    type Column = HashMap<EntityHandle, *const T>;
+    fn component() -> Option<(TypeId, bool)> { Some((TypeId::of::<T>(), false)) }
+    fn column(components: &mut Components) -> Option<Self::Column> {
+        Some(components.column::<T>()?.iter().map(|(h, v)| (*h, v as *const T)).collect())
+    }
+    unsafe fn fetch<'a>(column: &Self::Column, handle: &EntityHandle) -> Option<&'a T> {
+        column.get(handle).map(|v| &**v)
+    }
+}
+impl<T: 'static> QueryParam for &mut T {
+    type Item<'a> = &'a mut T;
+    type Column = HashMap<EntityHandle, *mut T>;
+    fn component() -> Option<(TypeId, bool)> { Some((TypeId::of::<T>(), true)) }
+    fn column(components: &mut Components) -> Option<Self::Column> {
+        // Each value is borrowed once here, rows only use the pointer for their own entity
+        Some(components.column_mut::<T>()?.iter_mut().map(|(h, v)| (*h, v as *mut T)).collect())
+    }
+    unsafe fn fetch<'a>(column: &Self::Column, handle: &EntityHandle) -> Option<&'a mut T> {
+        column.get(handle).map(|v| &mut **v)
+    }
+}
+impl QueryParam for EntityHandle {
+    type Item<'a> = EntityHandle;
+    type Column = ();
+    fn component() -> Option<(TypeId, bool)> { None }
+    fn column(_components: &mut Components) -> Option<Self::Column> { Some(()) }
+    unsafe fn fetch<'a>(_column: &Self::Column, handle: &EntityHandle) -> Option<Self::Item<'a>> {
+        Some(*handle)
+    }
+}
+
+/// Tuple of `QueryParam`s, only entities that have every component are returned
+pub trait Query {
+    type Item<'a>;
+    type Columns;
+    fn types() -> Vec<(TypeId, bool)>;
+    fn columns(components: &mut Components) -> Option<Self::Columns>;
+    /// # Safety
+    /// The components must still be alive and each entity may only be fetched once
+    unsafe fn fetch<'a>(columns: &Self::Columns, handle: &EntityHandle) -> Option<Self::Item<'a>>;
+}
+
+macro_rules! impl_query {
+    ($($param:ident $index:tt),+) => {
+        impl<$($param: QueryParam),+> Query for ($($param,)+) {
+            type Item<'a> = ($($param::Item<'a>,)+);
+            type Columns = ($($param::Column,)+);
+            fn types() -> Vec<(TypeId, bool)> {
+                [$($param::component()),+].into_iter().flatten().collect()
+            }
+            fn columns(components: &mut Components) -> Option<Self::Columns> {
+                Some(($($param::column(components)?,)+))
+            }
+            unsafe fn fetch<'a>(columns: &Self::Columns, handle: &EntityHandle) -> Option<Self::Item<'a>> {
+                Some(($($param::fetch(&columns.$index, handle)?,)+))
+            }
+        }
+    };
+}
+impl_query!(A 0);
+impl_query!(A 0, B 1);
+impl_query!(A 0, B 1, C 2);
+impl_query!(A 0, B 1, C 2, D 3);
+
+pub struct QueryIter<'a, Q: Query> {
+    columns: Option<Q::Columns>,
+    handles: Vec<EntityHandle>,
+    current: usize,
+    phantom: PhantomData<(&'a mut Components, Q)>,
+}
+impl<'a, Q: Query> Iterator for QueryIter<'a, Q> {
+    type Item = Q::Item<'a>;
+
+    fn next(&mut self) -> Option<Q::Item<'a>> {
+        let columns = self.columns.as_ref()?;
+        while self.current < self.handles.len() {
+            let handle = self.handles[self.current];
+            self.current += 1;
+
+            // Handles are unique and the query was checked for aliasing so each component is only borrowed once
+            if let Some(item) = unsafe { Q::fetch(columns, &handle) } {
+                return Some(item);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generational_array::GenerationalArray;
+
+    fn handles(count: usize) -> Vec<EntityHandle> {
+        let mut array = GenerationalArray::<(), 8>::new();
+        (0..count).map(|_| array.push(()).0).collect()
+    }
+
+    #[test]
+    fn mutable_queries_over_the_same_column() {
+        let h = handles(3);
+        let mut components = Components::new();
+        for (i, handle) in h.iter().enumerate() {
+            components.insert(*handle, i as u32);
+        }
+        components.insert(h[1], 10.0f32);
+
+        // Every row is alive at the same time
+        let rows = components.query::<(&mut u32,)>().collect::<Vec<_>>();
+        assert_eq!(rows.len(), 3);
+        for (value,) in rows {
+            *value += 1;
+        }
+
+        for (value, scale) in components.query::<(&mut u32, &f32)>() {
+            *value *= *scale as u32;
+        }
+
+        assert_eq!(components.get::<u32>(&h[0]), Some(&1));
+        assert_eq!(components.get::<u32>(&h[1]), Some(&20));
+        assert_eq!(components.get::<u32>(&h[2]), Some(&3));
+        assert_eq!(components.query::<(EntityHandle, &u32, &i8)>().count(), 0);
+    }
+}
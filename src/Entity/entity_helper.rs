@@ -1,7 +1,9 @@
 #![allow(dead_code)]
-use crate::physics::{RigidBody, RigidBodyHandle, PhysicsMaterial, CollisionShape};
+use crate::physics::{RigidBody, RigidBodyHandle, PhysicsMaterial, CollisionShape, BodyType};
 use crate::V2;
 use crate::entity::Rectangle;
+use crate::messages::MessageBus;
+use super::{DestroyReason, EntityCommands};
 
 pub struct EntityInitialization<'a> {
     pub(super) position: &'a mut V2,
@@ -9,6 +11,9 @@ pub struct EntityInitialization<'a> {
     pub(super) rotation: &'a mut f32, // radians
     pub(super) material: Option<PhysicsMaterial>,
     pub(super) shape: Option<CollisionShape>,
+    pub(super) sensor: bool,
+    pub(super) continuous: bool,
+    pub(super) body_type: BodyType,
     pub(super) layer: u8,
     pub(super) colliding_layers: u8,
 }
@@ -16,8 +21,29 @@ impl<'a> EntityInitialization<'a> {
     pub fn attach_rigid_body(&mut self, material: PhysicsMaterial, shape: CollisionShape) -> &mut EntityInitialization<'a> { 
         self.material = Some(material);
         self.shape = Some(shape);
+        self.sensor = false;
         self
     }
+    /// Attaches a shape that reports overlaps through `EntityBehavior::on_overlap` without being pushed by other bodies
+    pub fn attach_sensor(&mut self, shape: CollisionShape) -> &mut EntityInitialization<'a> {
+        self.material = None;
+        self.shape = Some(shape);
+        self.sensor = true;
+        self
+    }
+    /// Bodies are dynamic unless this is called, sensors ignore it
+    pub fn body_type(&mut self, body_type: BodyType) -> &mut EntityInitialization<'a> {
+        self.body_type = body_type;
+        self
+    }
+    /// Sweeps the body between steps so it can't pass through thin or small bodies when moving fast
+    pub fn continuous(&mut self) -> &mut EntityInitialization<'a> {
+        self.continuous = true;
+        self
+    }
+    pub fn has_body(&self) -> bool {
+        self.shape.is_some()
+    }
     pub fn collision_layer(&mut self, layer: impl std::convert::Into<u8>) -> &mut EntityInitialization<'a> {
         self.layer = layer.into();
         self
@@ -45,8 +71,10 @@ pub struct EntityUpdate<'a> {
     pub(super) position: &'a mut V2,
     pub(super) scale: &'a mut V2,
     pub(super) rotation: &'a mut f32, // radians
+    pub(super) local_position: &'a mut V2,
+    pub(super) local_rotation: &'a mut f32,
     pub(super) rigid_body: &'a mut Option<RigidBodyHandle>,
-    pub(super) mark_for_destroy: &'a mut bool,
+    pub(super) destroy_reason: &'a mut Option<DestroyReason>,
 }
 impl<'a> EntityUpdate<'a> {
     pub fn position(&self) -> V2 { *self.position }
@@ -60,6 +88,16 @@ impl<'a> EntityUpdate<'a> {
         *self.position += delta;
         self
     }
+    /// Position relative to the parent entity, parented entities should move with this instead of `set_position`
+    pub fn local_position(&self) -> V2 { *self.local_position }
+    pub fn set_local_position(&mut self, position: V2) -> &mut EntityUpdate<'a> {
+        *self.local_position = position;
+        self
+    }
+    pub fn set_local_rotation(&mut self, rotation: f32) -> &mut EntityUpdate<'a> {
+        *self.local_rotation = rotation;
+        self
+    }
     pub fn apply_force(&mut self, x: f32, y: f32) -> &mut EntityUpdate<'a> {
         if let Some(handle) = *self.rigid_body {
             RigidBody::get(handle).apply_force(V2::new(x, y));
@@ -78,6 +116,17 @@ impl<'a> EntityUpdate<'a> {
         self
     }
     pub fn destroy(&mut self) {
-        *self.mark_for_destroy = true;
+        self.destroy_with(DestroyReason::Destroyed);
     }
+    /// Only the first reason the entity is destroyed with is kept
+    pub fn destroy_with(&mut self, reason: DestroyReason) {
+        self.destroy_reason.get_or_insert(reason);
+    }
+}
+
+/// Passed to the lifecycle callbacks of `EntityBehavior`
+/// Entities spawned with `commands` are created at the next sync point
+pub struct EntityLifecycle<'a> {
+    pub messages: &'a mut MessageBus,
+    pub commands: &'a mut EntityCommands,
 }
\ No newline at end of file
@@ -1,29 +1,61 @@
 
- use super::{EntityBehavior, EntityHandle, Entity, MAX_ENTITIES};
+ use super::{EntityBehavior, EntityHandle, EntityId, EntityTag, EntityLifecycle, DestroyReason, Entity, SceneId, PERSISTENT_SCENE, ENTITY_CHUNK_SIZE, ENTITY_SOFT_LIMIT};
+ use super::components::{Components, Query, QueryIter};
+ use super::prefab::{Prefab, PrefabLibrary};
+ use super::commands::EntityCommands;
+ use crate::logger::PanicLogEntry;
  use crate::generational_array::{GenerationalArray, Iter};
- use crate::messages::MessageBus;
+ use crate::messages::{MessageBus, Subscriber, Target};
  use crate::V2;
- use std::collections::HashMap;
+ use std::collections::{HashMap, HashSet};
  use std::any::TypeId;
 
 crate::singleton!(entity_manager: EntityManager = EntityManager::new());
 
 pub enum EntityCreationOptions {
     None,
+    /// The only entity of its behavior type, found with `EntityManager::find_as`
     Tag,
-    //TODO persist
+    /// The entity is given each of these tags, see `EntityManager::add_tag`
+    Tagged(Vec<EntityTag>),
+    /// The entity isn't destroyed when its scene is unloaded, see `EntityManager::set_persistent`
+    Persist,
 }
 
 pub struct EntityManager {
-    entities: GenerationalArray<Entity, MAX_ENTITIES>,
-    tags: HashMap<TypeId, EntityHandle>,
+    entities: GenerationalArray<Entity, ENTITY_CHUNK_SIZE>,
+    /// Live entities in the order they were created, entities are updated in this order
+    order: Vec<EntityHandle>,
+    singletons: HashMap<TypeId, EntityHandle>,
+    tags: HashMap<EntityTag, Vec<EntityHandle>>,
+    /// Handles of every live entity grouped by behavior type
+    types: HashMap<EntityId, Vec<EntityHandle>>,
+    components: Components,
+    prefabs: PrefabLibrary,
+    commands: EntityCommands,
+    /// Entities that haven't had `on_spawn` called yet
+    spawned: Vec<EntityHandle>,
+    /// Entities that haven't been registered with the message bus yet
+    unregistered: Vec<EntityHandle>,
+    unloaded_scenes: HashSet<SceneId>,
+    current_scene: SceneId,
 }
 
 impl EntityManager {
     pub fn new() -> EntityManager {
         EntityManager { 
-            entities: GenerationalArray::new(),
+            entities: GenerationalArray::with_soft_limit(ENTITY_SOFT_LIMIT, |limit| crate::logger::warn!("More than {} entities are alive", limit)),
+            singletons: HashMap::new(),
             tags: HashMap::new(),
+            types: HashMap::new(),
+            components: Components::new(),
+            prefabs: PrefabLibrary::new(),
+            commands: EntityCommands::new(),
+            spawned: vec!(),
+            unregistered: vec!(),
+            unloaded_scenes: HashSet::new(),
+            order: vec!(),
+            current_scene: 0,
         }
     }
 
@@ -37,39 +69,259 @@ impl EntityManager {
         self.create_options_at(behavior, V2::new(0., 0.), options)
     }
     pub fn create_options_at(&mut self, behavior: impl EntityBehavior + 'static, position: V2, options: EntityCreationOptions) -> EntityHandle {
+        self.create_boxed(Box::new(behavior), position, options, None)
+    }
+
+    /// Creates an entity from a prefab in the prefab file, `None` if there is no prefab with that name
+    pub fn spawn_prefab(&mut self, name: &str, position: V2) -> Option<EntityHandle> {
+        let (behavior, prefab) = self.prefabs.instantiate(name)?;
+        Some(self.create_boxed(behavior, position, EntityCreationOptions::None, Some(&prefab)))
+    }
+    /// Same as `spawn_prefab` but `configure` can change the behavior before it is initialized
+    pub fn spawn_prefab_with<T: 'static>(&mut self, name: &str, position: V2, configure: impl FnOnce(&mut T)) -> Option<EntityHandle> {
+        let (mut behavior, prefab) = self.prefabs.instantiate(name)?;
+        configure(behavior.as_any_mut().downcast_mut::<T>().log_message_and_panic(&format!("Prefab {} has a different behavior type", name)));
+        Some(self.create_boxed(behavior, position, EntityCreationOptions::None, Some(&prefab)))
+    }
+    pub fn prefabs(&mut self) -> &mut PrefabLibrary {
+        &mut self.prefabs
+    }
+
+    pub(super) fn create_boxed(&mut self, behavior: Box<dyn EntityBehavior>, position: V2, options: EntityCreationOptions, prefab: Option<&Prefab>) -> EntityHandle {
         let id = behavior.id();
-        let entity = Entity::new(behavior, position);
+        let mut entity = Entity::from_box(behavior, position);
+        entity.scene = self.current_scene;
         let (handle, data) = self.entities.push(entity);
-        data.initialize();
+        data.initialize(handle, prefab);
+        self.order.push(handle);
+        self.spawned.push(handle);
+        self.unregistered.push(handle);
 
-        if let EntityCreationOptions::Tag = options { self.tags.insert(id, handle); }
+        match options {
+            EntityCreationOptions::Tag => {
+                if self.singletons.insert(id, handle).is_some() {
+                    crate::logger::warn!("Tagged entity was replaced by another entity of the same type");
+                }
+            },
+            EntityCreationOptions::Tagged(tags) => {
+                for t in tags { self.add_tag(handle, t); }
+            },
+            EntityCreationOptions::Persist => { self.set_persistent(handle, true); },
+            EntityCreationOptions::None => {},
+        }
+        self.types.entry(id).or_default().push(handle);
+
+        handle
+    }
 
+    /// Creates an entity attached to `parent` at `local_position` relative to it
+    pub fn create_child_at(&mut self, parent: EntityHandle, behavior: impl EntityBehavior + 'static, local_position: V2) -> EntityHandle {
+        let (position, rotation) = match self.get(&parent) {
+            Some(p) => (p.position + rotate(local_position, p.rotation), p.rotation),
+            None => (local_position, 0.),
+        };
+        let handle = self.create_at(behavior, position);
+        self.get_mut(&handle).unwrap().rotation += rotation;
+        self.set_parent(handle, Some(parent));
         handle
     }
 
+    /// Attaches `child` to `parent` keeping its current world position, `None` detaches it
+    /// Scale is not inherited from the parent
+    pub fn set_parent(&mut self, child: EntityHandle, parent: Option<EntityHandle>) -> bool {
+        if let Some(p) = parent {
+            // An entity can't be parented to one of its own descendants
+            let mut ancestor = Some(p);
+            while let Some(a) = ancestor {
+                if a == child { return false; }
+                ancestor = match self.get(&a) {
+                    Some(e) => e.parent,
+                    None => return false,
+                };
+            }
+        }
+
+        let old = match self.get(&child) {
+            Some(e) => e.parent,
+            None => return false,
+        };
+        if let Some(old) = old && let Some(e) = self.get_mut(&old) {
+            e.children.retain(|c| *c != child);
+        }
+
+        let (position, rotation) = match parent.and_then(|p| self.get_mut(&p)) {
+            Some(p) => {
+                p.children.push(child);
+                (p.position, p.rotation)
+            },
+            None => (V2::new(0., 0.), 0.),
+        };
+
+        let entity = self.get_mut(&child).unwrap();
+        entity.parent = parent;
+        entity.local_position = rotate(entity.position - position, -rotation);
+        entity.local_rotation = entity.rotation - rotation;
+        true
+    }
+
+    /// Moves every parented entity to its parent's position offset by its local transform
+    pub fn update_transforms(&mut self) {
+        for h in self.iter_handles() {
+            let entity = self.get(&h).unwrap();
+            if entity.parent.is_none() {
+                self.update_children(&h);
+            }
+        }
+    }
+    fn update_children(&mut self, parent: &EntityHandle) {
+        let entity = self.get(parent).unwrap();
+        let (position, rotation) = (entity.position, entity.rotation);
+        for c in entity.children.clone() {
+            if let Some(child) = self.get_mut(&c) {
+                child.position = position + rotate(child.local_position, rotation);
+                child.rotation = rotation + child.local_rotation;
+                self.update_children(&c);
+            }
+        }
+    }
+
     pub fn iter_handles(&self) -> Vec<EntityHandle> {
-        self.entities.iter_index()
+        self.order.clone()
+    }
+
+    /// Queue for changes that should happen at the next sync point instead of immediately
+    pub fn defer(&mut self) -> &mut EntityCommands {
+        self.commands.scene = self.current_scene;
+        &mut self.commands
+    }
+    /// Applies everything queued with `defer` in the order it was queued
+    pub fn apply_commands(&mut self) {
+        let scene = self.current_scene;
+        while !self.commands.is_empty() {
+            let commands = self.commands.take();
+            EntityCommands::apply(commands, self, scene);
+        }
+        self.current_scene = scene;
+
+        // Commands can only be queued for an unloaded scene by its entities, once they are gone the id is never seen again
+        if !self.unloaded_scenes.is_empty() {
+            let entities = &self.entities;
+            self.unloaded_scenes.retain(|s| entities.iter().any(|e| e.scene == *s));
+        }
     }
-    pub fn iter(&self) -> Iter<Entity, MAX_ENTITIES> {
+    pub fn iter(&self) -> Iter<Entity, ENTITY_CHUNK_SIZE> {
         self.entities.iter()
     }
 
+    /// Registers every entity created since the last call with the message bus
+    /// The bus may already be borrowed when an entity is created so this happens at sync points instead
+    pub fn register_messages(&mut self, messages: &mut MessageBus) {
+        for h in std::mem::take(&mut self.unregistered) {
+            if let Some(entity) = self.entities.get_mut(&h) {
+                entity.behavior.register(messages, Subscriber::Entity(h));
+            }
+        }
+    }
+
     pub fn dispose_entities(&mut self, messages: &mut MessageBus) {
+        self.register_messages(messages);
+        for h in std::mem::take(&mut self.spawned) {
+            if let Some(entity) = self.entities.get_mut(&h) {
+                self.commands.scene = entity.scene;
+                entity.notify_spawn(&mut EntityLifecycle { messages, commands: &mut self.commands });
+            }
+        }
+
+        // Children are destroyed along with their parent
         for h in self.iter_handles() {
-            let entity = self.get(&h).unwrap();
-            if entity.mark_for_destroy {
+            if self.get(&h).unwrap().is_destroyed() {
+                self.destroy_children(&h);
+            }
+        }
+
+        for h in self.iter_handles() {
+            let entity = self.entities.get_mut(&h).unwrap();
+            if entity.is_destroyed() {
+                self.commands.scene = entity.scene;
+                entity.notify_destroy(&mut EntityLifecycle { messages, commands: &mut self.commands });
+
                 let id = entity.behavior.id();
+                let parent = entity.parent;
+                let tags = entity.tags.clone();
                 if let Some(r) = entity.rigid_body {
                     crate::physics::RigidBody::destroy(r);
                 }
 
-                entity.behavior.unregister(messages);
+                messages.unsubscribe_all(&Subscriber::Entity(h));
+                messages.cancel(&Target::Entity(h));
                 self.entities.remove(&h);
-                self.tags.remove(&id);
-                
+                if self.singletons.get(&id) == Some(&h) {
+                    self.singletons.remove(&id);
+                }
+                for t in &tags {
+                    self.remove_from_tag(&h, t);
+                }
+                self.components.remove_entity(&h);
+                if let Some(p) = parent && let Some(parent) = self.entities.get_mut(&p) {
+                    parent.children.retain(|c| *c != h);
+                }
+                if let Some(handles) = self.types.get_mut(&id) {
+                    handles.retain(|other| *other != h);
+                }
+            }
+        }
+
+        let entities = &self.entities;
+        self.order.retain(|h| entities.get(h).is_some());
+    }
+    fn destroy_children(&mut self, parent: &EntityHandle) {
+        for c in self.get(parent).unwrap().children.clone() {
+            if let Some(child) = self.get_mut(&c) {
+                child.destroy_with(DestroyReason::Parent);
+                self.destroy_children(&c);
+            }
+        }
+    }
+
+    /// Persistent entities and their children are kept alive across scene changes
+    /// Revoking persistence gives the entity to the current scene so it's destroyed when that scene unloads
+    pub fn set_persistent(&mut self, handle: EntityHandle, persistent: bool) {
+        let scene = if persistent { PERSISTENT_SCENE } else { self.current_scene };
+        if let Some(entity) = self.get_mut(&handle) {
+            entity.scene = scene;
+            entity.paused = false;
+            for c in entity.children.clone() {
+                self.set_persistent(c, persistent);
+            }
+        }
+    }
+
+    /// Entities created from now on will belong to this scene
+    pub(super) fn set_current_scene(&mut self, scene: SceneId) {
+        self.current_scene = scene;
+    }
+    pub(super) fn set_scene_paused(&mut self, scene: SceneId, paused: bool) {
+        for h in self.iter_handles() {
+            let entity = self.get_mut(&h).unwrap();
+            if entity.scene == scene { entity.paused = paused; }
+        }
+    }
+    pub(super) fn destroy_scene(&mut self, scene: SceneId, messages: &mut MessageBus) {
+        self.unloaded_scenes.insert(scene);
+        for h in self.iter_handles() {
+            let entity = self.entities.get_mut(&h).unwrap();
+            if entity.scene == scene || entity.persistent() {
+                self.commands.scene = entity.scene;
+                entity.notify_scene_unload(&mut EntityLifecycle { messages, commands: &mut self.commands });
             }
+            if entity.scene == scene { entity.destroy_with(DestroyReason::SceneUnload); }
         }
     }
+    /// Whether the scene has been unloaded, anything queued for it is dropped
+    pub(super) fn scene_unloaded(&self, scene: SceneId) -> bool {
+        self.unloaded_scenes.contains(&scene)
+    }
+
     pub fn get(&self, handle: &EntityHandle) -> Option<&Entity> {
         self.entities.get(handle)
     }
@@ -77,10 +329,66 @@ impl EntityManager {
         self.entities.get_mut(handle)
     }
 
+    /// Every live entity with the behavior `T`, without scanning the other entities
+    pub fn handles_of<T: 'static>(&self) -> &[EntityHandle] {
+        self.types.get(&TypeId::of::<T>()).map(|h| h.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn add_component<T: 'static>(&mut self, handle: EntityHandle, component: T) {
+        if self.entities.get(&handle).is_some() {
+            self.components.insert(handle, component);
+        }
+    }
+    pub fn remove_component<T: 'static>(&mut self, handle: &EntityHandle) -> Option<T> {
+        self.components.remove(handle)
+    }
+    pub fn component<T: 'static>(&self, handle: &EntityHandle) -> Option<&T> {
+        self.components.get(handle)
+    }
+    pub fn component_mut<T: 'static>(&mut self, handle: &EntityHandle) -> Option<&mut T> {
+        self.components.get_mut(handle)
+    }
+    /// Iterates every entity that has all of the components in `Q`
+    /// ```
+    /// for (health, velocity) in entities.query::<(&Health, &mut Velocity)>() { }
+    /// ```
+    pub fn query<Q: Query>(&mut self) -> QueryIter<'_, Q> {
+        self.components.query::<Q>()
+    }
+
+    pub fn add_tag(&mut self, handle: EntityHandle, tag: impl Into<EntityTag>) {
+        let tag = tag.into();
+        if let Some(entity) = self.entities.get_mut(&handle) && !entity.tags.contains(&tag) {
+            entity.tags.push(tag.clone());
+            self.tags.entry(tag).or_default().push(handle);
+        }
+    }
+    pub fn remove_tag(&mut self, handle: EntityHandle, tag: impl Into<EntityTag>) {
+        let tag = tag.into();
+        if let Some(entity) = self.entities.get_mut(&handle) {
+            entity.tags.retain(|t| *t != tag);
+            self.remove_from_tag(&handle, &tag);
+        }
+    }
+    fn remove_from_tag(&mut self, handle: &EntityHandle, tag: &EntityTag) {
+        if let Some(handles) = self.tags.get_mut(tag) {
+            handles.retain(|h| h != handle);
+            if handles.is_empty() { self.tags.remove(tag); }
+        }
+    }
+    /// Every entity with the tag
+    pub fn find_all_tagged(&self, tag: impl Into<EntityTag>) -> &[EntityHandle] {
+        self.tags.get(&tag.into()).map(|h| h.as_slice()).unwrap_or(&[])
+    }
+    /// First entity given the tag
+    pub fn find_tagged(&self, tag: impl Into<EntityTag>) -> Option<EntityHandle> {
+        self.find_all_tagged(tag).first().copied()
+    }
+
     pub fn find_as<T: 'static>(&self) -> Option<&T> {
         let address = std::any::TypeId::of::<T>();
 
-        if let Some(handle) = self.tags.get(&address) {
+        if let Some(handle) = self.singletons.get(&address) {
             let entity = self.entities.get(handle).unwrap();
             return entity.as_any().downcast_ref::<T>();
         }
@@ -90,13 +398,18 @@ impl EntityManager {
     pub fn find_as_mut<T: 'static>(&mut self) -> Option<&T> {
         let address = std::any::TypeId::of::<T>();
         
-        if let Some(handle) = self.tags.get(&address) {
+        if let Some(handle) = self.singletons.get(&address) {
             let entity = self.entities.get_mut(handle).unwrap();
             return entity.as_any().downcast_ref::<T>();
         }
         None
     }
 }
+fn rotate(v: V2, radians: f32) -> V2 {
+    let (s, c) = radians.sin_cos();
+    V2::new(v.x * c - v.y * s, v.x * s + v.y * c)
+}
+
 impl Default for EntityManager {
     fn default() -> Self { Self::new() }
 }
\ No newline at end of file
@@ -1,40 +1,87 @@
-use crate::physics::{RigidBody, RigidBodyHandle};
+use crate::physics::{RigidBody, RigidBodyHandle, Contact, BodyType};
 use crate::assets::Images;
 use crate::{utils::Rectangle, V2, Graphics, UpdateState};
 
 mod scene;
 mod manager;
 mod entity_helper;
-pub use self::scene::{SceneBehavior, SceneLoad, Scene};
+mod transition;
+mod components;
+mod prefab;
+mod tags;
+mod commands;
+pub use self::scene::{SceneBehavior, SceneLoad, Scene, SceneId, PERSISTENT_SCENE};
+pub use transition::Transition;
 pub use manager::{EntityManager, entity_manager, EntityCreationOptions};
-pub use entity_helper::{EntityInitialization, EntityUpdate};
+pub use entity_helper::{EntityInitialization, EntityUpdate, EntityLifecycle};
+pub use prefab::{Prefab, PrefabLibrary};
+pub use tags::EntityTag;
+pub use commands::EntityCommands;
 
-pub const MAX_ENTITIES: usize = 512;
+/// Entities are allocated in chunks of this many entities
+pub const ENTITY_CHUNK_SIZE: usize = 512;
+/// A warning is logged whenever the number of live entities goes past this
+pub const ENTITY_SOFT_LIMIT: usize = 4096;
 
 pub type EntityId = std::any::TypeId;
 pub type EntityHandle = crate::generational_array::GenerationalIndex;
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DestroyReason {
+    /// Destroyed without a more specific reason
+    Destroyed,
+    Killed,
+    OutOfBounds,
+    /// The scene the entity belongs to was unloaded
+    SceneUnload,
+    /// The entity's parent was destroyed
+    Parent,
+}
+
 pub struct Entity {
     pub position: crate::V2,
     pub scale: crate::V2,
     pub rotation: f32, // radians
+    previous_position: crate::V2,
+    previous_rotation: f32,
+    /// Position and rotation relative to the parent, only used when `parent` is set
+    local_position: crate::V2,
+    local_rotation: f32,
+    parent: Option<EntityHandle>,
+    children: Vec<EntityHandle>,
+    tags: Vec<EntityTag>,
     pub rigid_body: Option<RigidBodyHandle>,
-    mark_for_destroy: bool,
+    destroy_reason: Option<DestroyReason>,
+    scene: SceneId,
+    paused: bool,
+    /// Boxed so the message bus can keep pointers to it while the entity is alive
     behavior: Box<dyn EntityBehavior>,
 }
 impl Entity {
     pub fn new(behvaior: impl EntityBehavior + 'static, position: V2) -> Entity {
+        Self::from_box(Box::new(behvaior), position)
+    }
+    pub(super) fn from_box(behavior: Box<dyn EntityBehavior>, position: V2) -> Entity {
         Entity {
             position,
             scale: V2::new(0., 0.),
             rotation: 0.,
-            behavior: Box::new(behvaior),
-            mark_for_destroy: false,
+            previous_position: position,
+            previous_rotation: 0.,
+            local_position: V2::new(0., 0.),
+            local_rotation: 0.,
+            parent: None,
+            children: vec!(),
+            tags: vec!(),
+            behavior,
+            destroy_reason: None,
+            scene: 0,
+            paused: false,
             rigid_body: None,
         }
 
     }
-    pub(super) fn initialize(&mut self) {
+    pub(super) fn initialize(&mut self, handle: EntityHandle, prefab: Option<&Prefab>) {
         let self_pointer = self as *mut Entity;
         let mut helper = EntityInitialization {
             position: &mut self.position,
@@ -42,37 +89,80 @@ impl Entity {
             rotation: &mut self.rotation,
             material: None,
             shape: None,
+            sensor: false,
+            continuous: false,
+            body_type: BodyType::Dynamic,
             layer: 1,
             colliding_layers: 1,
         };
+        if let Some(p) = prefab { p.initialize_scale(&mut helper); }
         self.behavior.initialize(&mut helper);
-        
-        if let Some(material) = helper.material {
+        if let Some(p) = prefab { p.initialize_body(&mut helper); }
+
+        if let Some(shape) = helper.shape {
             let layer = helper.layer;
             let colliding_layers = helper.colliding_layers;
-            let rigid_body = RigidBody::attach(self_pointer, material, helper.shape.unwrap(), layer, colliding_layers);
+            let rigid_body = match helper.material {
+                Some(material) => RigidBody::attach(self_pointer, handle, material, shape, helper.body_type, layer, colliding_layers),
+                None => RigidBody::attach_sensor(self_pointer, handle, shape, layer, colliding_layers),
+            };
+            RigidBody::get(rigid_body).set_continuous(helper.continuous);
             self.rigid_body = Some(rigid_body);
         }
+        self.previous_position = self.position;
+        self.previous_rotation = self.rotation;
     }
     pub(super) fn update(&mut self, state: &mut UpdateState) {
-        let mut helper = EntityUpdate {
-            position: &mut self.position,
-            scale: &mut self.scale,
-            rotation: &mut self.rotation,
-            rigid_body: &mut self.rigid_body,
-            mark_for_destroy: &mut self.mark_for_destroy
-        };
-        self.behavior.update(&mut helper, state)
+        self.previous_position = self.position;
+        self.previous_rotation = self.rotation;
+
+        let (behavior, mut helper) = self.split();
+        behavior.update(&mut helper, state)
+    }
+    pub(super) fn notify_collision_enter(&mut self, other: &Entity, contact: &Contact, messages: &mut crate::messages::MessageBus) {
+        let (behavior, mut helper) = self.split();
+        behavior.on_collision_enter(&mut helper, other, contact, messages);
+    }
+    pub(super) fn notify_collision_stay(&mut self, other: &Entity, contact: &Contact, messages: &mut crate::messages::MessageBus) {
+        let (behavior, mut helper) = self.split();
+        behavior.on_collision_stay(&mut helper, other, contact, messages);
+    }
+    pub(super) fn notify_collision_exit(&mut self, other: EntityHandle, messages: &mut crate::messages::MessageBus) {
+        let (behavior, mut helper) = self.split();
+        behavior.on_collision_exit(&mut helper, other, messages);
+    }
+    pub(super) fn notify_overlap(&mut self, other: &Entity, messages: &mut crate::messages::MessageBus) {
+        let (behavior, mut helper) = self.split();
+        behavior.on_overlap(&mut helper, other, messages);
+    }
+    pub(super) fn notify_overlap_exit(&mut self, other: EntityHandle, messages: &mut crate::messages::MessageBus) {
+        let (behavior, mut helper) = self.split();
+        behavior.on_overlap_exit(&mut helper, other, messages);
     }
-    pub(super) fn notify_collision(&mut self, other: &Entity, messages: &mut crate::messages::MessageBus) {
-        let mut helper = EntityUpdate {
+    pub(super) fn notify_spawn(&mut self, context: &mut EntityLifecycle) {
+        let (behavior, mut helper) = self.split();
+        behavior.on_spawn(&mut helper, context);
+    }
+    pub(super) fn notify_destroy(&mut self, context: &mut EntityLifecycle) {
+        let reason = self.destroy_reason.unwrap_or(DestroyReason::Destroyed);
+        let (behavior, mut helper) = self.split();
+        behavior.on_destroy(&mut helper, reason, context);
+    }
+    pub(super) fn notify_scene_unload(&mut self, context: &mut EntityLifecycle) {
+        let (behavior, mut helper) = self.split();
+        behavior.on_scene_unload(&mut helper, context);
+    }
+    fn split(&mut self) -> (&mut dyn EntityBehavior, EntityUpdate<'_>) {
+        let helper = EntityUpdate {
             position: &mut self.position,
             scale: &mut self.scale,
             rotation: &mut self.rotation,
+            local_position: &mut self.local_position,
+            local_rotation: &mut self.local_rotation,
             rigid_body: &mut self.rigid_body,
-            mark_for_destroy: &mut self.mark_for_destroy
+            destroy_reason: &mut self.destroy_reason
         };
-        self.behavior.on_collision(&mut helper, other, messages);
+        (self.behavior.as_mut(), helper)
     }
     pub fn as_any(&self) -> &dyn std::any::Any {
         self.behavior.as_any()
@@ -81,11 +171,41 @@ impl Entity {
         self.behavior.as_any_mut()
     }
     pub fn destroy(&mut self) {
-        self.mark_for_destroy = true;
+        self.destroy_with(DestroyReason::Destroyed);
+    }
+    /// Marks the entity to be disposed at the end of the update, only the first reason is kept
+    pub fn destroy_with(&mut self, reason: DestroyReason) {
+        self.destroy_reason.get_or_insert(reason);
     }
+    pub fn is_destroyed(&self) -> bool { self.destroy_reason.is_some() }
     pub fn bounds(&self) -> crate::utils::Rectangle {
         crate::utils::Rectangle::new(self.position, self.scale)
     }
+    /// Scene that created this entity
+    pub fn scene(&self) -> SceneId { self.scene }
+    /// Persistent entities survive their scene being unloaded
+    pub fn persistent(&self) -> bool { self.scene == PERSISTENT_SCENE }
+    /// Paused entities belong to a scene that is covered by another scene and aren't updated
+    pub fn paused(&self) -> bool { self.paused }
+    /// Position blended between the last two simulation steps
+    pub fn interpolated_position(&self, alpha: f32) -> V2 {
+        self.previous_position + (self.position - self.previous_position) * alpha
+    }
+    pub fn interpolated_rotation(&self, alpha: f32) -> f32 {
+        self.previous_rotation + (self.rotation - self.previous_rotation) * alpha
+    }
+    pub fn interpolated_bounds(&self, alpha: f32) -> Rectangle {
+        Rectangle::new(self.interpolated_position(alpha), self.scale)
+    }
+    /// Entity this entity's position and rotation are relative to
+    pub fn parent(&self) -> Option<EntityHandle> { self.parent }
+    pub fn children(&self) -> &[EntityHandle] { &self.children }
+    pub fn tags(&self) -> &[EntityTag] { &self.tags }
+    pub fn has_tag(&self, tag: impl Into<EntityTag>) -> bool { self.tags.contains(&tag.into()) }
+    pub fn local_position(&self) -> V2 { self.local_position }
+    pub fn local_rotation(&self) -> f32 { self.local_rotation }
+    pub fn set_local_position(&mut self, position: V2) { self.local_position = position; }
+    pub fn set_local_rotation(&mut self, rotation: f32) { self.local_rotation = rotation; }
 }
 
 pub trait EntityBehavior: crate::messages::MessageHandler {
@@ -98,15 +218,58 @@ pub trait EntityBehavior: crate::messages::MessageHandler {
     fn update(&mut self, e: &mut EntityUpdate, update_state: &mut UpdateState);
     fn render(&self, e: &Entity, graphics: &mut Graphics);
 
-    fn on_collision(&mut self, _e: &mut EntityUpdate, _other: &Entity, _messages: &mut crate::messages::MessageBus) { }
+    /// Called on the step this entity's body starts touching another body
+    fn on_collision_enter(&mut self, _e: &mut EntityUpdate, _other: &Entity, _contact: &Contact, _messages: &mut crate::messages::MessageBus) { }
+    /// Called on every following step the bodies are still touching
+    fn on_collision_stay(&mut self, _e: &mut EntityUpdate, _other: &Entity, _contact: &Contact, _messages: &mut crate::messages::MessageBus) { }
+    /// Called on the step the bodies stop touching, `other` may have been destroyed
+    fn on_collision_exit(&mut self, _e: &mut EntityUpdate, _other: EntityHandle, _messages: &mut crate::messages::MessageBus) { }
+    /// Called when this entity's body starts overlapping a body and one of them is a sensor
+    fn on_overlap(&mut self, _e: &mut EntityUpdate, _other: &Entity, _messages: &mut crate::messages::MessageBus) { }
+    /// Called when the bodies stop overlapping, `other` may have been destroyed
+    fn on_overlap_exit(&mut self, _e: &mut EntityUpdate, _other: EntityHandle, _messages: &mut crate::messages::MessageBus) { }
+
+    /// Called at the end of the update the entity was created in
+    fn on_spawn(&mut self, _e: &mut EntityUpdate, _context: &mut EntityLifecycle) { }
+    /// Called right before the entity is disposed
+    fn on_destroy(&mut self, _e: &mut EntityUpdate, _reason: DestroyReason, _context: &mut EntityLifecycle) { }
+    /// Called when the entity's scene is unloaded, persistent entities are told about every scene that unloads
+    fn on_scene_unload(&mut self, _e: &mut EntityUpdate, _context: &mut EntityLifecycle) { }
 
     fn render_texture(&self, image: Images, e: &Entity, graphics: &mut Graphics) {
         if let Some(i) = crate::assets::request_image(graphics, image) {
-            graphics.draw_image(Rectangle::new(e.position, e.scale), i);
+            let bounds = e.interpolated_bounds(graphics.interpolation());
+            graphics.draw_image(bounds, i);
         }
     }
 }
 
+/// Delivers a message to an entity
+/// The behavior is taken out of its entity while handling the message so the context can't be used to reach it again
+pub(crate) fn process_message(handle: EntityHandle, message: &crate::messages::Message, context: &mut crate::messages::MessageContext) {
+    let mut behavior = match context.entities.get_mut(&handle) {
+        Some(e) => std::mem::replace(&mut e.behavior, Box::new(Vacant)),
+        None => return,
+    };
+    behavior.process(message, context);
+
+    // The entity is only removed from the manager in dispose_entities
+    context.entities.get_mut(&handle).unwrap().behavior = behavior;
+}
+
+/// Stands in for a behavior that has been taken out of its entity
+struct Vacant;
+impl crate::messages::MessageHandler for Vacant {
+    fn process(&mut self, _: &crate::messages::Message, _: &mut crate::messages::MessageContext) { }
+    fn register(&self, _: &mut crate::messages::MessageBus, _: crate::messages::Subscriber) { }
+}
+impl EntityBehavior for Vacant {
+    crate::entity!(Vacant);
+    fn initialize(&mut self, _: &mut EntityInitialization) { }
+    fn update(&mut self, _: &mut EntityUpdate, _: &mut UpdateState) { }
+    fn render(&self, _: &Entity, _: &mut Graphics) { }
+}
+
 #[macro_export]
 macro_rules! entity {
     ($ty:ty) => {
@@ -0,0 +1,258 @@
+#![allow(dead_code)]
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use crate::physics::{PhysicsMaterial, CollisionShape, Circle, Polygon, BodyType};
+use crate::graphics::{AnimationPlayer, SpriteSheetOrientation};
+use crate::assets::Images;
+use crate::logger::{PanicLogEntry, info};
+use crate::{V2, V2U};
+use super::{EntityBehavior, EntityInitialization};
+
+#[derive(Debug)]
+pub enum PrefabError {
+    IoError(std::io::Error),
+    ParseError(ron::error::SpannedError),
+    /// A layer name that wasn't registered with `PrefabLibrary::register_layers`
+    UnknownLayer(String),
+}
+impl From<std::io::Error> for PrefabError {
+    fn from(error: std::io::Error) -> Self {
+        PrefabError::IoError(error)
+    }
+}
+impl From<ron::error::SpannedError> for PrefabError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        PrefabError::ParseError(error)
+    }
+}
+type PrefabResult<T> = Result<T, PrefabError>;
+
+#[derive(Deserialize, Clone)]
+pub enum PrefabShape {
+    Circle(f32),
+    Rectangle(f32, f32),
+    /// Circle with a diameter of the entity's width
+    FitCircle,
+    /// Rectangle the size of the entity
+    FitRectangle,
+}
+
+/// Physics layer written as the bare name of one of the registered layers
+#[derive(Clone, Debug)]
+pub struct LayerName(pub String);
+impl<'de> Deserialize<'de> for LayerName {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Names are written like enum variants since bare identifiers are only read as variant names
+        deserializer.deserialize_enum("LayerName", &[], LayerVisitor)
+    }
+}
+
+struct LayerVisitor;
+impl<'de> serde::de::Visitor<'de> for LayerVisitor {
+    type Value = LayerName;
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "a physics layer name")
+    }
+    fn visit_str<E: serde::de::Error>(self, name: &str) -> Result<LayerName, E> {
+        Ok(LayerName(String::from(name)))
+    }
+    fn visit_enum<A: serde::de::EnumAccess<'de>>(self, data: A) -> Result<LayerName, A::Error> {
+        let (name, variant) = data.variant_seed(LayerVisitor)?;
+        serde::de::VariantAccess::unit_variant(variant)?;
+        Ok(name)
+    }
+}
+impl<'de> serde::de::DeserializeSeed<'de> for LayerVisitor {
+    type Value = LayerName;
+    fn deserialize<D: serde::Deserializer<'de>>(self, deserializer: D) -> Result<LayerName, D::Error> {
+        deserializer.deserialize_identifier(self)
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct PrefabBody {
+    /// Bodies without a material are sensors
+    #[serde(default)]
+    pub material: Option<PhysicsMaterial>,
+    pub shape: PrefabShape,
+    #[serde(default)]
+    pub body_type: BodyType,
+    pub layer: LayerName,
+    #[serde(default)]
+    pub collides_with: Vec<LayerName>,
+    /// Fast bodies should be continuous so they can't pass through other bodies
+    #[serde(default)]
+    pub continuous: bool,
+    /// Layer values looked up when the prefabs are loaded
+    #[serde(skip)]
+    layer_bits: u8,
+    #[serde(skip)]
+    colliding_bits: u8,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct PrefabAnimation {
+    pub index: u32,
+    pub frame_count: u32,
+    pub frame_size: (u32, u32),
+    pub frame_duration: f32,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct PrefabSprite {
+    pub image: Images,
+    pub orientation: SpriteSheetOrientation,
+    /// Animation name to animation, names are parsed as the player's key type
+    #[serde(default)]
+    pub animations: HashMap<String, PrefabAnimation>,
+}
+
+/// Description of an entity loaded from the prefab file
+#[derive(Deserialize, Clone)]
+pub struct Prefab {
+    /// Name the behavior was registered with using `PrefabLibrary::register`
+    pub behavior: String,
+    #[serde(default)]
+    pub scale: Option<(f32, f32)>,
+    #[serde(default)]
+    pub body: Option<PrefabBody>,
+    #[serde(default)]
+    pub sprite: Option<PrefabSprite>,
+    /// Tunable values read by the behavior
+    #[serde(default)]
+    pub parameters: HashMap<String, f32>,
+}
+impl Prefab {
+    pub fn parameter(&self, name: &str) -> f32 {
+        *self.parameters.get(name).log_message_and_panic(&format!("Prefab {} is missing parameter {}", self.behavior, name))
+    }
+
+    /// Builds an animation player from the sprite sheet in the prefab
+    pub fn animation_player<T: Eq + Hash + DeserializeOwned>(&self) -> AnimationPlayer<T> {
+        let sprite = self.sprite.as_ref().log_message_and_panic(&format!("Prefab {} has no sprite", self.behavior));
+
+        let mut player = AnimationPlayer::new(sprite.image, sprite.orientation);
+        for (name, a) in &sprite.animations {
+            let key = ron::from_str::<T>(name).log_message_and_panic(&format!("Unknown animation {}", name));
+            player.add(key, a.index, a.frame_count, V2U::new(a.frame_size.0, a.frame_size.1), a.frame_duration);
+        }
+        player
+    }
+
+    /// Applied before the behavior is initialized so it can still change the scale
+    pub(super) fn initialize_scale(&self, e: &mut EntityInitialization) {
+        if let Some((x, y)) = self.scale {
+            e.set_scale(x, y);
+        }
+    }
+
+    /// Applied after the behavior is initialized, does nothing if the behavior attached its own rigid body
+    pub(super) fn initialize_body(&self, e: &mut EntityInitialization) {
+        if e.has_body() { return; }
+
+        if let Some(body) = &self.body {
+            let scale = *e.scale;
+            let shape = match body.shape {
+                PrefabShape::Circle(radius) => CollisionShape::Circle(Circle::new(radius)),
+                PrefabShape::Rectangle(width, height) => CollisionShape::Polygon(Polygon::rectangle(width, height, V2::new(0., 0.))),
+                PrefabShape::FitCircle => CollisionShape::Circle(Circle::new(scale.x / 2.)),
+                PrefabShape::FitRectangle => CollisionShape::Polygon(Polygon::rectangle(scale.x, scale.y, V2::new(0., 0.))),
+            };
+            match body.material {
+                Some(material) => e.attach_rigid_body(material, shape),
+                None => e.attach_sensor(shape),
+            }.body_type(body.body_type)
+             .collision_layer(body.layer_bits)
+             .collides_with(body.colliding_bits);
+            if body.continuous { e.continuous(); }
+        }
+    }
+}
+
+pub type PrefabFactory = fn(&Prefab) -> Box<dyn EntityBehavior>;
+
+/// Prefabs loaded from a RON file of prefab name to `Prefab`
+/// The file is reloaded when it changes, entities created after that use the new values
+pub struct PrefabLibrary {
+    prefabs: HashMap<String, Prefab>,
+    factories: HashMap<String, PrefabFactory>,
+    layers: HashMap<&'static str, u8>,
+    path: PathBuf,
+    last_write: SystemTime,
+}
+impl PrefabLibrary {
+    pub fn new() -> PrefabLibrary {
+        PrefabLibrary {
+            prefabs: HashMap::new(),
+            factories: HashMap::new(),
+            layers: HashMap::new(),
+            path: PathBuf::default(),
+            last_write: SystemTime::now(),
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> PrefabResult<()> {
+        self.path = path.as_ref().to_path_buf();
+        self.last_write = std::fs::metadata(&self.path)?.modified()?;
+        self.prefabs = self.parse(&std::fs::read_to_string(&self.path)?)?;
+        Ok(())
+    }
+
+    /// Checks for and loads any changes to the prefab file
+    pub fn update(&mut self) -> PrefabResult<bool> {
+        if self.path.as_path().exists() {
+            let last_write = std::fs::metadata(&self.path)?.modified()?;
+
+            if last_write > self.last_write {
+                self.last_write = last_write;
+                // Keep the old prefabs if the new file can't be parsed
+                self.prefabs = self.parse(&std::fs::read_to_string(&self.path)?)?;
+                info!("Reloaded prefabs from {:?}", self.path);
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn parse(&self, text: &str) -> PrefabResult<HashMap<String, Prefab>> {
+        let mut prefabs: HashMap<String, Prefab> = ron::from_str(text)?;
+        for p in prefabs.values_mut() {
+            if let Some(body) = &mut p.body {
+                body.layer_bits = self.layer(&body.layer)?;
+                body.colliding_bits = 0;
+                for l in &body.collides_with {
+                    body.colliding_bits |= self.layer(l)?;
+                }
+            }
+        }
+        Ok(prefabs)
+    }
+    fn layer(&self, name: &LayerName) -> PrefabResult<u8> {
+        self.layers.get(name.0.as_str()).copied().ok_or_else(|| PrefabError::UnknownLayer(name.0.clone()))
+    }
+
+    pub fn register(&mut self, behavior: &str, factory: PrefabFactory) {
+        self.factories.insert(String::from(behavior), factory);
+    }
+    /// Layer names that can be used in the prefab file, must be called before the file is loaded
+    pub fn register_layers(&mut self, layers: &[(&'static str, u8)]) {
+        self.layers.extend(layers.iter().copied());
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Prefab> {
+        self.prefabs.get(name)
+    }
+
+    pub(super) fn instantiate(&self, name: &str) -> Option<(Box<dyn EntityBehavior>, Prefab)> {
+        let prefab = self.prefabs.get(name)?;
+        let factory = self.factories.get(&prefab.behavior).log_message_and_panic(&format!("No behavior registered for {}", prefab.behavior));
+        Some((factory(prefab), prefab.clone()))
+    }
+}
+impl Default for PrefabLibrary {
+    fn default() -> Self { Self::new() }
+}
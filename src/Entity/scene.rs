@@ -1,91 +1,364 @@
 use crate::Graphics;
-use super::{EntityManager, UpdateState};
+use super::{Entity, EntityManager, UpdateState};
 use crate::job_system::ThreadSafeJobQueue;
-use crate::messages::{SharedMessageBus, MessageContext};
-use crate::ui::Widget;
+use crate::messages::{SharedMessageBus, MessageContext, MessageHandler, Subscriber};
+use crate::ui::{Widget, Root};
+use super::transition::{Transition, ActiveTransition, TransitionPhase};
+use crate::assets::AssetManifest;
+use crate::graphics::Color;
+use crate::utils::Rectangle;
+use crate::V2;
+use std::collections::HashMap;
+
+pub type SceneId = u32;
+/// Scene of entities created with `EntityCreationOptions::Persist`, they are never unloaded with a scene
+pub const PERSISTENT_SCENE: SceneId = SceneId::MAX;
 
 pub enum SceneLoad {
+    /// Unloads every scene on the stack and loads the new one
     Load(Box<dyn SceneBehavior>),
+    /// Same as `Load` but animated, input is blocked until the transition finishes
+    Transition(Box<dyn SceneBehavior>, Transition),
+    /// Loads a scene on top of the current one
+    Push(Box<dyn SceneBehavior>),
+    /// Unloads the top scene and loads a new one in its place
+    #[allow(dead_code)]
+    Replace(Box<dyn SceneBehavior>),
+    /// Unloads the top scene, returning to the one below it
+    Pop,
     Unload,
     None,
 }
 
-pub struct Scene {
+struct SceneLayer {
+    id: SceneId,
     behavior: Box<dyn SceneBehavior>,
+    /// Assets that are still loading, the scene isn't loaded until this is empty
+    manifest: Option<AssetManifest>,
+}
+
+/// Stack of scenes, the top scene is the one receiving input
+/// Each scene owns the entities created while it is loading or updating and
+/// a child of the root widget that is passed to it as its own root
+pub struct Scene {
+    layers: Vec<SceneLayer>,
+    next_id: SceneId,
+    transition: Option<ActiveTransition>,
 }
 impl Scene {
-    pub fn new(behavior: Box<dyn SceneBehavior>) -> Scene {
-        Scene { behavior }
+    pub fn new() -> Scene {
+        Scene { layers: vec!(), next_id: 0, transition: None }
     }
-    
-    pub fn load(&mut self, queue: ThreadSafeJobQueue, messages: SharedMessageBus, entities: &mut EntityManager, root: &mut Widget) {
+
+    pub fn load(&mut self, behavior: Box<dyn SceneBehavior>, queue: ThreadSafeJobQueue, messages: SharedMessageBus, entities: &mut EntityManager, root: &mut Widget) {
+        while !self.layers.is_empty() {
+            self.pop(entities, &messages, root);
+        }
+        self.push(behavior, queue, &messages, entities, root);
+    }
+
+    fn push(&mut self, behavior: Box<dyn SceneBehavior>, queue: ThreadSafeJobQueue, messages: &SharedMessageBus, entities: &mut EntityManager, root: &mut Widget) {
+        let manifest = behavior.assets();
+        manifest.request(&queue);
+
+        let mut layer = SceneLayer { id: self.next_id, behavior, manifest: Some(manifest) };
+        self.next_id += 1;
+
+        // Scene widgets are added directly so their index always matches the layer index
+        root.children_mut().push(Widget::new(Root {}, None));
+        let widget = root.children_mut().last_mut().unwrap();
+
+        // Anything already cached can load right away without showing the loading screen
+        Self::try_finish_loading(&mut layer, queue, messages, entities, widget);
+
+        self.layers.push(layer);
+        self.refresh_layers(entities);
+    }
+
+    fn try_finish_loading(layer: &mut SceneLayer, queue: ThreadSafeJobQueue, messages: &SharedMessageBus, entities: &mut EntityManager, root: &mut Widget) -> bool {
+        match &layer.manifest {
+            Some(m) if m.is_loaded() => {},
+            Some(_) => return false,
+            None => return true,
+        }
+        layer.manifest = None;
+
+        entities.set_current_scene(layer.id);
         let mut m = messages.borrow_mut();
-        self.behavior.load(queue, entities, root);
-        self.behavior.register(&mut m)
+        layer.behavior.load(queue, entities, root);
+        layer.behavior.register(&mut m, Subscriber::Scene(layer.id));
+        true
+    }
+
+    fn pop(&mut self, entities: &mut EntityManager, messages: &SharedMessageBus, root: &mut Widget) {
+        if !self.layers.is_empty() {
+            self.remove_layer(self.layers.len() - 1, entities, messages, root);
+        }
+        self.refresh_layers(entities);
     }
 
-    fn unload(&mut self, entities: &mut EntityManager, messages: &SharedMessageBus, root: &mut Widget) {
+    fn remove_layer(&mut self, index: usize, entities: &mut EntityManager, messages: &SharedMessageBus, root: &mut Widget) {
+        let mut layer = self.layers.remove(index);
         let mut m = messages.borrow_mut();
-        self.behavior.unload();
-        self.behavior.unregister(&mut m);
-    
-        // Unload entities
-        for h in entities.iter_handles() {
-            let entity = entities.get_mut(&h).unwrap();
-            entity.destroy();
+        if layer.manifest.is_none() {
+            layer.behavior.unload();
+            m.unsubscribe_all(&Subscriber::Scene(layer.id));
+        }
+
+        entities.destroy_scene(layer.id, &mut m);
+        root.children_mut().remove(index).unregister_messages(&mut m);
+    }
+
+    fn start_transition(&mut self, behavior: Box<dyn SceneBehavior>, transition: Transition, state: &mut crate::UpdateState, root: &mut Widget) {
+        if let Some(top) = self.layers.last_mut() {
+            top.behavior.transition_started();
+        }
+
+        let mut active = ActiveTransition { transition, phase: TransitionPhase::Out, time: 0., next: None, outgoing: 0 };
+        if active.is_crossfade() {
+            // Crossfades need both scenes at once so the new scene goes on top of the old ones
+            active.phase = TransitionPhase::In;
+            active.outgoing = self.layers.len();
+            self.push(behavior, state.queue.clone(), &state.message_bus, state.entities, root);
+            self.layers.last_mut().unwrap().behavior.transition_started();
+        } else {
+            active.next = Some(behavior);
+        }
+        self.transition = Some(active);
+    }
+
+    fn update_transition(&mut self, state: &mut crate::UpdateState, root: &mut Widget) {
+        let transition = match &mut self.transition {
+            Some(t) => t,
+            None => return,
+        };
+
+        transition.time += state.delta_time;
+        if transition.progress() < 1. {
+            return;
+        }
+
+        match transition.phase {
+            TransitionPhase::Out => {
+                // Screen is fully covered, swap the scenes and start revealing the new one
+                let next = transition.next.take().unwrap();
+                transition.phase = TransitionPhase::In;
+                transition.time = 0.;
+                self.load(next, state.queue.clone(), state.message_bus.clone(), state.entities, root);
+                self.layers.last_mut().unwrap().behavior.transition_started();
+            },
+            TransitionPhase::In => {
+                let outgoing = transition.outgoing;
+                self.transition = None;
+                for _ in 0..outgoing {
+                    self.remove_layer(0, state.entities, &state.message_bus, root);
+                }
+                self.refresh_layers(state.entities);
+                self.layers.last_mut().unwrap().behavior.transition_finished();
+            },
+        }
+    }
+
+    /// Index of the lowest layer that is still updating
+    fn first_updating_layer(&self) -> usize {
+        let mut index = self.layers.len().saturating_sub(1);
+        while index > 0 && self.layers[index].behavior.update_below() {
+            index -= 1;
         }
+        index
+    }
 
-        // Unload UI
-        for c in root.children_mut() {
-            c.destroy();
+    /// Index of the lowest layer that is still visible
+    fn first_visible_layer(&self) -> usize {
+        let mut index = self.layers.len().saturating_sub(1);
+        while index > 0 && self.layers[index].behavior.render_below() {
+            index -= 1;
+        }
+        index
+    }
+
+    fn refresh_layers(&self, entities: &mut EntityManager) {
+        let first = self.first_updating_layer();
+        for (i, l) in self.layers.iter().enumerate() {
+            entities.set_scene_paused(l.id, i < first);
+        }
+        if let Some(top) = self.layers.last() {
+            entities.set_current_scene(top.id);
         }
     }
 
+    /// True while the top scene is waiting on its assets or a transition is blocking input
+    pub fn is_loading(&self) -> bool {
+        self.transition.is_some() || self.layers.last().is_some_and(|l| l.manifest.is_some())
+    }
+
     pub fn update(&mut self, state: &mut crate::UpdateState, root: &mut Widget) -> bool {
         root.layout(&crate::graphics::screen_rect());
-        root.update(state);
 
-        let load = self.behavior.update(state, root);
-        
+        if self.transition.is_some() {
+            state.consume_all_actions();
+        }
+
+        // Only the top scene is able to change the stack
+        let Some(top) = self.layers.len().checked_sub(1) else {
+            return false;
+        };
+        let mut load = SceneLoad::None;
+        for i in self.first_updating_layer()..=top {
+            let layer = &mut self.layers[i];
+            let widget = &mut root.children_mut()[i];
+            if !Self::try_finish_loading(layer, state.queue.clone(), &state.message_bus, state.entities, widget) {
+                continue;
+            }
+            widget.update(state);
+
+            state.entities.set_current_scene(layer.id);
+            let l = layer.behavior.update(state, widget);
+            if i == top { load = l; }
+        }
+
+        // Scenes can't be changed while a transition is running
+        if self.transition.is_some() {
+            load = SceneLoad::None;
+        }
+
+        // Anything queued by the scenes is applied before the stack changes so it's created in the scene that queued it
+        if !matches!(load, SceneLoad::None) || self.transition.is_some() {
+            state.entities.apply_commands();
+        }
+        self.update_transition(state, root);
+
         match load {
-            SceneLoad::Load(b) => { 
-                self.unload(state.entities, &state.message_bus, root);
-                self.behavior = b;
-                self.load(state.queue.clone(), state.message_bus.clone(), state.entities, root);
+            SceneLoad::Load(b) => {
+                self.load(b, state.queue.clone(), state.message_bus.clone(), state.entities, root);
+                true
+            },
+            SceneLoad::Transition(b, t) => {
+                self.start_transition(b, t, state, root);
+                true
+            },
+            SceneLoad::Push(b) => {
+                self.push(b, state.queue.clone(), &state.message_bus, state.entities, root);
+                true
+            },
+            SceneLoad::Replace(b) => {
+                self.pop(state.entities, &state.message_bus, root);
+                self.push(b, state.queue.clone(), &state.message_bus, state.entities, root);
                 true
-             },
+            },
+            SceneLoad::Pop => {
+                self.pop(state.entities, &state.message_bus, root);
+                !self.layers.is_empty()
+            },
             SceneLoad::Unload => {
-                self.unload(state.entities, &state.message_bus, root);
+                while !self.layers.is_empty() {
+                    self.pop(state.entities, &state.message_bus, root);
+                }
                 false
             },
-            SceneLoad::None => { 
+            SceneLoad::None => {
                 //TODO i dont like this
                 let entities = crate::entity::entity_manager();
                 for h in entities.iter_handles() {
-                    let entity = entities.get_mut(&h).unwrap();
-                    entity.update(state);
+                    let entity = entities.get(&h).unwrap();
+                    if !entity.paused {
+                        // Anything spawned by a persistent entity belongs to the top scene
+                        let scene = if entity.persistent() { self.layers.last().unwrap().id } else { entity.scene };
+                        entities.set_current_scene(scene);
+                        entities.get_mut(&h).unwrap().update(state);
+                    }
                 }
+                entities.set_current_scene(self.layers.last().unwrap().id);
+                entities.apply_commands();
+
                 let mut m = state.message_bus.borrow_mut();
+                entities.register_messages(&mut m);
+                let mut scenes = self.layers.iter_mut()
+                    .filter(|l| l.manifest.is_none())
+                    .map(|l| (l.id, l.behavior.as_mut() as &mut dyn MessageHandler))
+                    .collect::<Vec<_>>();
                 let mut context = MessageContext { root, entities };
-                m.process_messages(&mut context);
+                m.process_messages(&mut scenes, &mut context);
                 true
             },
         }
     }
 
     pub fn render(&self, graphics: &mut crate::Graphics, root: &Widget, entities: &EntityManager) {
+        let crossfade = self.transition.as_ref().filter(|t| t.is_crossfade());
+        let first = if crossfade.is_some() { 0 } else { self.first_visible_layer() };
+        // Persistent entities go on top of the lowest visible scene so overlays like the pause menu cover them
+        let base = self.first_visible_layer();
+
+        let mut by_scene: HashMap<SceneId, Vec<&Entity>> = HashMap::new();
         for entity in entities.iter() {
-            entity.behavior.render(entity, graphics);
+            by_scene.entry(entity.scene).or_default().push(entity);
         }
-        self.behavior.render(graphics);
+        let render_entities = |scene: SceneId, graphics: &mut crate::Graphics| {
+            for entity in by_scene.get(&scene).into_iter().flatten() {
+                entity.behavior.render(entity, graphics);
+            }
+        };
+
+        let rect = crate::graphics::screen_rect();
+        for i in first..self.layers.len() {
+            let opacity = match crossfade {
+                Some(t) if i >= t.outgoing => t.progress(),
+                _ => 1.,
+            };
+            graphics.set_opacity(opacity);
+
+            let layer = &self.layers[i];
+            if let Some(m) = &layer.manifest {
+                layer.behavior.render_loading(graphics, m.progress());
+            } else {
+                render_entities(layer.id, graphics);
+                layer.behavior.render(graphics);
+                root.children()[i].render(graphics, &rect);
+            }
 
-        root.render(graphics, &crate::graphics::screen_rect())
+            if i == base {
+                graphics.set_opacity(1.);
+                render_entities(PERSISTENT_SCENE, graphics);
+            }
+        }
+        graphics.set_opacity(1.);
+
+        if let Some(t) = &self.transition {
+            t.render(graphics);
+        }
     }
 }
+impl Default for Scene {
+    fn default() -> Self { Self::new() }
+}
 
 pub trait SceneBehavior: crate::messages::MessageHandler {
     fn load(&mut self, queue: ThreadSafeJobQueue, entities: &mut EntityManager, root: &mut crate::ui::Widget);
     fn unload(&mut self);
     fn update(&mut self, update_state: &mut UpdateState, root: &mut crate::ui::Widget) -> SceneLoad;
     fn render(&self, graphics: &mut Graphics);
+
+    /// Assets that are loaded on the job system before `load` is called
+    fn assets(&self) -> AssetManifest { AssetManifest::new() }
+    /// Drawn instead of the scene while its assets are loading
+    fn render_loading(&self, graphics: &mut Graphics, progress: f32) {
+        let screen = crate::graphics::screen_rect();
+        graphics.draw_rectangle(screen.clone(), Color::BLACK);
+
+        let size = V2::new(screen.width() * 0.5, 20.);
+        let position = screen.top_left() + (screen.size() - size) / 2.;
+        graphics.draw_rectangle(Rectangle::new(position, size), Color::GRAY);
+        graphics.draw_rectangle(Rectangle::new(position, V2::new(size.x * progress, size.y)), Color::WHITE);
+    }
+
+    /// Whether scenes below this one on the stack are still rendered
+    fn render_below(&self) -> bool { false }
+    /// Whether scenes below this one on the stack are still updated
+    fn update_below(&self) -> bool { false }
+
+    /// Called when a transition away from or into this scene begins
+    fn transition_started(&mut self) { }
+    /// Called on the incoming scene once a transition has finished
+    fn transition_finished(&mut self) { }
 }
@@ -0,0 +1,35 @@
+use std::borrow::Cow;
+
+/// Label that can be given to any number of entities, an entity can have any number of tags
+/// Tags are created from strings or from enums declared with `entity_tag_enum!`
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct EntityTag(Cow<'static, str>);
+impl EntityTag {
+    pub fn name(&self) -> &str { &self.0 }
+}
+impl From<&'static str> for EntityTag {
+    fn from(name: &'static str) -> Self { EntityTag(Cow::Borrowed(name)) }
+}
+impl From<String> for EntityTag {
+    fn from(name: String) -> Self { EntityTag(Cow::Owned(name)) }
+}
+
+#[macro_export]
+macro_rules! entity_tag_enum {
+    ($(#[$meta:meta])* $vis:vis enum $name:ident {
+        $($key:ident,)+
+    }) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+        $vis enum $name {
+            $($key,)+
+        }
+        impl std::convert::From<$name> for $crate::entity::EntityTag {
+            fn from(tag: $name) -> Self {
+                match tag {
+                    $($name::$key => $crate::entity::EntityTag::from(concat!(stringify!($name), "::", stringify!($key))),)+
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,85 @@
+#![allow(dead_code)]
+use crate::graphics::{Graphics, Color};
+use crate::utils::Rectangle;
+use crate::V2;
+
+pub enum TransitionType {
+    /// Fades to a color, loads the next scene, then fades back in
+    Fade(Color),
+    /// Covers the screen left to right, loads the next scene, then uncovers it
+    Wipe(Color),
+    /// Loads the next scene immediately and fades it in over the current one
+    Crossfade,
+}
+
+/// How a `SceneLoad::Transition` moves to the next scene
+/// `duration` is the length of each phase, so fades and wipes take twice as long as a crossfade
+pub struct Transition {
+    pub(super) transition_type: TransitionType,
+    pub(super) duration: f32,
+}
+impl Transition {
+    pub fn fade(duration: f32) -> Transition {
+        Transition { transition_type: TransitionType::Fade(Color::BLACK), duration }
+    }
+    pub fn fade_color(duration: f32, color: Color) -> Transition {
+        Transition { transition_type: TransitionType::Fade(color), duration }
+    }
+    pub fn wipe(duration: f32) -> Transition {
+        Transition { transition_type: TransitionType::Wipe(Color::BLACK), duration }
+    }
+    pub fn crossfade(duration: f32) -> Transition {
+        Transition { transition_type: TransitionType::Crossfade, duration }
+    }
+}
+
+#[derive(PartialEq, Eq)]
+pub(super) enum TransitionPhase {
+    Out,
+    In,
+}
+
+pub(super) struct ActiveTransition {
+    pub(super) transition: Transition,
+    pub(super) phase: TransitionPhase,
+    pub(super) time: f32,
+    pub(super) next: Option<Box<dyn super::SceneBehavior>>,
+    /// Number of layers that are being crossfaded out
+    pub(super) outgoing: usize,
+}
+impl ActiveTransition {
+    pub(super) fn progress(&self) -> f32 {
+        if self.transition.duration <= 0. { return 1.; }
+        f32::min(self.time / self.transition.duration, 1.)
+    }
+
+    pub(super) fn is_crossfade(&self) -> bool {
+        matches!(self.transition.transition_type, TransitionType::Crossfade)
+    }
+
+    /// Draws anything that covers the scenes, crossfades are handled by the scene opacity instead
+    pub(super) fn render(&self, graphics: &mut Graphics) {
+        // How much of the screen is covered
+        let amount = match self.phase {
+            TransitionPhase::Out => self.progress(),
+            TransitionPhase::In => 1. - self.progress(),
+        };
+
+        let screen = crate::graphics::screen_rect();
+        match self.transition.transition_type {
+            TransitionType::Fade(color) => {
+                let color = Color::new(color.r(), color.g(), color.b(), color.a() * amount);
+                graphics.draw_rectangle(screen, color);
+            },
+            TransitionType::Wipe(color) => {
+                let width = screen.width() * amount;
+                let rect = match self.phase {
+                    TransitionPhase::Out => Rectangle::new(screen.top_left(), V2::new(width, screen.height())),
+                    TransitionPhase::In => Rectangle::from_coords(V2::new(screen.right() - width, screen.top()), screen.bottom_right()),
+                };
+                graphics.draw_rectangle(rect, color);
+            },
+            TransitionType::Crossfade => {},
+        }
+    }
+}
@@ -6,6 +6,7 @@ use crate::utils::Rectangle;
 use crate::{Graphics, V2, V2U, assets::Images};
 use crate::utils::Timer;
 
+#[derive(serde::Deserialize, Clone, Copy)]
 pub enum SpriteSheetOrientation {
     Horizontal,
     Vertical,
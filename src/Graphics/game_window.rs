@@ -31,7 +31,9 @@ pub fn create_window(event_loop: &EventLoop<()>, builder: WindowBuilder, queue:
         objects: vec!(),
         index_count: 0,
         vertex_count: 0,
-        z_index: 0.
+        z_index: 0.,
+        interpolation: 1.,
+        opacity: 1.,
     }
 }
 
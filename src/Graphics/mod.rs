@@ -67,6 +67,8 @@ pub struct Graphics {
     vertex_count: usize,
     index_count: usize,
     z_index: f32,
+    interpolation: f32,
+    opacity: f32,
 }
 impl Graphics {
     pub fn load_image(&self, image: glium::texture::RawImage2d<u8>) -> ImageHandle {
@@ -150,7 +152,16 @@ impl Graphics {
     fn write_vert_and_ind(&mut self, verts: &[Vertex], inds: &[u16]) {
         let vertices = self.vertices.slice_mut(self.vertex_count..(self.vertex_count + verts.len()))
             .expect("Vertex count has exceeded max vertex count");
-        vertices.write(verts);
+        if self.opacity < 1. {
+            let mut mapping = vertices.map_write();
+            for (i, v) in verts.iter().enumerate() {
+                let mut v = *v;
+                v.color[3] *= self.opacity;
+                mapping.set(i, v);
+            }
+        } else {
+            vertices.write(verts);
+        }
         self.vertex_count += verts.len();
 
         if !inds.is_empty() {
@@ -198,6 +209,19 @@ impl Graphics {
     pub fn z_index(&mut self, z_index: f32) {
         self.z_index = z_index;
     }
+
+    /// Fraction of a simulation step that has elapsed since the last update, used to smooth rendering
+    pub fn interpolation(&self) -> f32 {
+        self.interpolation
+    }
+    pub fn set_interpolation(&mut self, interpolation: f32) {
+        self.interpolation = interpolation;
+    }
+
+    /// Multiplies the alpha of everything drawn until it is reset to 1
+    pub fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity;
+    }
 }
 
 
@@ -46,5 +46,5 @@ impl crate::ui::UiElement for Manager {
 }
 // impl MessageHandler for Manager {
 //     crate::handle_messages!();
-//     fn process(&mut self, _message: &Messages) {}
+//     fn process(&mut self, _message: &Message) {}
 // }
\ No newline at end of file
@@ -8,15 +8,13 @@ pub enum EnemyType {
 }
 
 use std::hash::Hash;
-use crate::{V2U, V2};
-use crate::entity::{Entity, EntityInitialization, EntityUpdate, EntityBehavior};
-use crate::physics::{PhysicsMaterial, Circle, CollisionShape};
-use crate::messages::{MessageHandler, Messages, MessageContext};
-use crate::graphics::{AnimationPlayer, SpriteSheetOrientation};
-use crate::assets::Images;
-use crate::gust::PhysicsLayers;
+use crate::V2;
+use crate::entity::{Entity, EntityInitialization, EntityUpdate, EntityBehavior, EntityLifecycle, DestroyReason, Prefab};
+use crate::messages::{MessageHandler, Message, MessageContext};
+use crate::gust::messages::{EnemyGotToEnd, EnemyKilled};
+use crate::graphics::AnimationPlayer;
 
-#[derive(Hash, Eq, PartialEq)]
+#[derive(Hash, Eq, PartialEq, serde::Deserialize)]
 enum SlimeAnimation {
     Idle,
     Walking,
@@ -27,21 +25,18 @@ pub struct Enemy {
     health: u32,
     health_bar: health_bar::HealthBar,
     progress: f32,
+    speed: f32,
     animation: AnimationPlayer<SlimeAnimation>
 }
 impl Enemy {
-    pub fn new() -> Enemy {
-        let mut animation = AnimationPlayer::new(Images::Slime, SpriteSheetOrientation::Vertical);
-        animation.add(SlimeAnimation::Idle, 2, 6, V2U::new(36, 32), 0.2);
-        animation.add(SlimeAnimation::Walking, 1, 6, V2U::new(36, 32), 0.1);
-        animation.add(SlimeAnimation::Hurt, 5, 3, V2U::new(36, 32), 0.5);
-
-        let health = 100;
+    pub fn from_prefab(prefab: &Prefab) -> Enemy {
+        let health = prefab.parameter("health");
         Enemy { 
-            health,
-            health_bar: health_bar::HealthBar::new(health as f32),
+            health: health as u32,
+            health_bar: health_bar::HealthBar::new(health),
             progress: 0.,
-            animation
+            speed: prefab.parameter("speed"),
+            animation: prefab.animation_player(),
         }
     }
 
@@ -55,11 +50,7 @@ impl EntityBehavior for Enemy {
     
     fn initialize(&mut self, e: &mut EntityInitialization) {
         let size = crate::gust::level::Layout::grid_size();
-        let shape = CollisionShape::Circle(Circle::new(size / 2.));
-        e.set_scale(size, size)
-         .attach_rigid_body(PhysicsMaterial::METAL, shape)
-         .collision_layer(PhysicsLayers::Enemy)
-         .collides_with(PhysicsLayers::Player as u8 | PhysicsLayers::Bullet as u8);
+        e.set_scale(size, size);
         self.animation.play(SlimeAnimation::Idle);
     }
 
@@ -69,31 +60,36 @@ impl EntityBehavior for Enemy {
         e.set_position(layout.get_position(self.progress));
         self.animation.update(state);
         self.health_bar.update(state.delta_time);
-        self.progress += state.delta_time * 0.1;
+        self.progress += state.delta_time * self.speed;
 
         if self.progress >= 1. {
-            state.send_message(Messages::EnemyGotToEnd(10));
+            state.send_message(EnemyGotToEnd(10));
             e.destroy();
         }
     }
     fn render(&self, e: &Entity, graphics: &mut crate::Graphics) {
-        self.animation.render(graphics, crate::utils::Rectangle::new(e.position, e.scale));
+        let bounds = e.interpolated_bounds(graphics.interpolation());
+        self.animation.render(graphics, bounds.clone());
 
-        let position = V2::new(e.position.x + e.scale.x / 2., e.position.y);
+        let position = V2::new(bounds.left() + e.scale.x / 2., bounds.top());
         self.health_bar.render(position, graphics);
     }
-    fn on_collision(&mut self, e: &mut EntityUpdate, other: &Entity, messages: &mut crate::messages::MessageBus) {
+    fn on_overlap(&mut self, e: &mut EntityUpdate, other: &Entity, _messages: &mut crate::messages::MessageBus) {
         if self.health > 0 &&
             let Some(b) = crate::utils::entity_as::<crate::gust::tower::Bullet>(other) {
            self.take_damage(b.damage());
             if self.health == 0 {
-                messages.send(Messages::EnemyKilled);
-                e.destroy();
+                e.destroy_with(DestroyReason::Killed);
             }
         }
     }
+    fn on_destroy(&mut self, _e: &mut EntityUpdate, reason: DestroyReason, context: &mut EntityLifecycle) {
+        if reason == DestroyReason::Killed {
+            context.messages.send(EnemyKilled);
+        }
+    }
 }
 impl MessageHandler for Enemy {
     crate::handle_messages!();
-    fn process(&mut self, _message: &Messages, _context: &mut MessageContext) {}
+    fn process(&mut self, _message: &Message, _context: &mut MessageContext) {}
 }
\ No newline at end of file
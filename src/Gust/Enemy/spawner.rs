@@ -3,6 +3,8 @@ use crate::entity::EntityManager;
 use crate::utils::Timer;
 
 pub struct Wave {
+    /// Every enemy in the wave is tagged with this
+    tag: String,
     enemy_count: u32,
     spawned_count: u32,
     spawn_interval: Timer,
@@ -11,6 +13,7 @@ pub struct Wave {
 impl Wave {
     pub fn new(enemy_count: u32, spawn_interval: f32, enemy_type: EnemyType) -> Wave {
         Wave {
+            tag: String::new(),
             enemy_count,
             spawned_count: 0,
             spawn_interval: Timer::new(spawn_interval),
@@ -24,10 +27,12 @@ impl Wave {
         }
 
         if self.spawn_interval.update(delta_time) {
-            let entity = match self.enemy_type {
-                EnemyType::Slime => crate::gust::enemy::Enemy::new(),
+            let prefab = match self.enemy_type {
+                EnemyType::Slime => "slime",
             };
-            entities.create(entity);
+            if let Some(h) = entities.spawn_prefab(prefab, crate::V2::new(0., 0.)) {
+                entities.add_tag(h, self.tag.clone());
+            }
             self.spawned_count += 1
         }
     }
@@ -53,7 +58,8 @@ impl EnemySpawner {
         }
     }
 
-    pub fn add_wave(&mut self, wave: Wave, spawn_start: f32) {
+    pub fn add_wave(&mut self, mut wave: Wave, spawn_start: f32) {
+        wave.tag = format!("wave_{}", self.waves.len() + 1);
         self.total_enemies += wave.enemy_count;
         self.waves.push(wave);
         self.intervals.push(spawn_start);
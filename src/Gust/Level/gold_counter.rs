@@ -0,0 +1,50 @@
+use crate::messages::Message;
+use crate::gust::messages::EnemyKilled;
+use crate::assets::Fonts;
+use crate::graphics::Color;
+use crate::ui::{LabeledValue, WidgetHelper};
+use crate::utils::Rectangle;
+use crate::{UpdateState, Graphics};
+
+const KILL_REWARD: u32 = 10;
+
+/// Shows the player's gold, which is earned directly from `EnemyKilled` messages
+pub struct GoldCounter {
+    label: LabeledValue<u32>,
+}
+impl GoldCounter {
+    pub fn new(gold: u32) -> GoldCounter {
+        GoldCounter { label: LabeledValue::new("Gold", gold, Fonts::Regular, 24., Color::WHITE) }
+    }
+
+    /// Returns false if there isn't enough gold
+    pub fn spend(&mut self, cost: u32) -> bool {
+        let gold = self.label.value();
+        if cost > gold {
+            return false;
+        }
+        self.label.set_value(gold - cost);
+        true
+    }
+}
+impl crate::ui::UiElement for GoldCounter {
+    fn as_any(&self) -> &dyn std::any::Any { self }
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any { self }
+
+    fn layout(&mut self, rect: &Rectangle, helper: &mut WidgetHelper) -> Rectangle {
+        self.label.layout(rect, helper)
+    }
+    fn render(&self, graphics: &mut Graphics, rect: &Rectangle) {
+        self.label.render(graphics, rect)
+    }
+    fn update(&mut self, _state: &mut UpdateState, _helper: &mut WidgetHelper, _rect: &Rectangle) { }
+
+    crate::handle_messages!(EnemyKilled);
+
+    fn process(&mut self, message: &Message, _helper: &mut WidgetHelper) {
+        if message.is::<EnemyKilled>() {
+            let gold = self.label.value();
+            self.label.set_value(gold + KILL_REWARD);
+        }
+    }
+}
@@ -2,7 +2,7 @@ use crate::V2;
 use cgmath::MetricSpace;
 use crate::graphics::Color;
 use crate::entity::{Entity, EntityInitialization, EntityUpdate};
-use crate::messages::{MessageHandler, Messages, MessageContext};
+use crate::messages::{MessageHandler, Message, MessageContext};
 use crate::utils::Rectangle;
 
 pub struct Layout { 
@@ -68,5 +68,5 @@ impl crate::entity::EntityBehavior for Layout {
 }
 impl MessageHandler for Layout {
     crate::handle_messages!();
-    fn process(&mut self, _message: &Messages, _context: &mut MessageContext) {}
+    fn process(&mut self, _message: &Message, _context: &mut MessageContext) {}
 }
\ No newline at end of file
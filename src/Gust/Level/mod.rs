@@ -1,15 +1,19 @@
 use crate::entity::{SceneBehavior, SceneLoad, EntityCreationOptions, EntityManager, EntityHandle};
 use crate::gust::{player::Player, enemy::EnemySpawner, tower::Tower, enemy::Wave, cards::{Manager, Card, Modifier, ModifierType}};
 use crate::job_system::ThreadSafeJobQueue;
-use crate::messages::{Messages, MessageHandler, MessageContext};
-use crate::assets::{Fonts};
+use crate::messages::{Message, MessageHandler, MessageContext};
+use crate::gust::messages::{EnemyGotToEnd, EnemyKilled};
+use crate::assets::{Fonts, Images, Sounds, DataFiles, AssetManifest};
+use crate::logger::PanicLogEntry;
 use crate::ui::{LabeledValue, HorizontalAlignment, VerticalAlignment, DockContainer};
 use crate::input::Actions;
 use crate::graphics::Color;
 use std::collections::HashMap;
 
 mod layout;
+mod gold_counter;
 pub use layout::Layout;
+use gold_counter::GoldCounter;
 
 const MAX_HEALTH: u32 = 100;
 
@@ -23,7 +27,6 @@ pub struct Level {
     spawner: EnemySpawner,
     status: LevelStatus,
     indicator: Option<EntityHandle>,
-    gold: u32,
 }
 impl Level {
     pub fn new() -> Level {
@@ -31,37 +34,39 @@ impl Level {
             spawner: EnemySpawner::new(),
             status: LevelStatus::InProgress,
             indicator: None,
-            gold: 100
         }
     }
 
     fn try_purchase(&mut self, cost: u32, root: &mut crate::ui::Widget) -> bool {
-        if cost > self.gold {
-            return false
+        match root.find_mut::<GoldCounter>("gold") {
+            Some(g) => g.spend(cost),
+            None => false,
         }
-
-        self.gold -= cost;
-        if let Some(g) = root.find_mut::<LabeledValue<u32>>("gold") {
-            g.set_value(self.gold);
-        }
-        true
     }
 
     fn handle_tower(&mut self, state: &mut crate::UpdateState, root: &mut crate::ui::Widget) {
         if state.action_pressed(Actions::SpawnTower1) && self.try_purchase(25, root) {
-            let tower = Tower::new(10., 5., 200.);
-            state.entities.create_at(tower, state.mouse_pos());
+            state.entities.spawn_prefab("tower", state.mouse_pos());
         }
 
         //TODO collision checking
     }
 }
 impl SceneBehavior for Level {
-    fn load(&mut self, _queue: ThreadSafeJobQueue, entities: &mut EntityManager, root: &mut crate::ui::Widget) {    
+    fn assets(&self) -> AssetManifest {
+        AssetManifest::new()
+            .image(Images::Slime)
+            .image(Images::Testing)
+            .font(Fonts::Regular)
+            .sound(Sounds::Shoot)
+            .data(DataFiles::Levels)
+    }
+
+    fn load(&mut self, queue: ThreadSafeJobQueue, entities: &mut EntityManager, root: &mut crate::ui::Widget) {    
         root.with_named_child(LabeledValue::new("Health", MAX_HEALTH, Fonts::Regular, 24., Color::WHITE), "health")
             .with_h_alignment(HorizontalAlignment::Left)
             .with_v_alignment(VerticalAlignment::Top);
-        root.with_named_child(LabeledValue::new("Gold", 0u32, Fonts::Regular, 24., Color::WHITE), "gold")
+        root.with_named_child(GoldCounter::new(100), "gold")
             .with_h_alignment(HorizontalAlignment::Right)
             .with_v_alignment(VerticalAlignment::Top);
 
@@ -73,8 +78,8 @@ impl SceneBehavior for Level {
         root.with_named_child(Manager::new(cards), "card_manager")
             .with_v_alignment(VerticalAlignment::Bottom);
 
-        let file = std::fs::read_to_string("./resources/levels.ron").unwrap();
-        let v = ron::from_str::<HashMap<&str, crate::gust::game_data::Level>>(&file).unwrap();
+        let file = crate::assets::request_data(&queue, DataFiles::Levels).log_message_and_panic("Level data was not preloaded");
+        let v = ron::from_str::<HashMap<&str, crate::gust::game_data::Level>>(file).log_and_panic();
         let level = &v["level_1"];
 
         for w in &level.waves {
@@ -89,6 +94,10 @@ impl SceneBehavior for Level {
     }
     fn unload(&mut self) {}
     fn update(&mut self, state: &mut crate::UpdateState, root: &mut crate::ui::Widget) -> SceneLoad {
+        if state.action_pressed(Actions::Cancel) {
+            return SceneLoad::Push(Box::new(crate::gust::pause_menu::PauseMenu::new()));
+        }
+
         self.spawner.update(state.delta_time, state.entities);
         self.handle_tower(state, root);
 
@@ -100,7 +109,7 @@ impl SceneBehavior for Level {
             if let Some(i) = self.indicator && 
                let Some(e) = state.entities.get_mut(&i) {
                 e.destroy();
-                root.remove("stats");
+                root.remove("stats", &mut state.message_bus.borrow_mut());
             }
 
             if entities.len() == 1 {
@@ -113,7 +122,7 @@ impl SceneBehavior for Level {
                     .add_child(LabeledValue::new("Damage", t.damage(), Fonts::Regular, 24., Color::WHITE))
                     .add_child(LabeledValue::new("Attack Rate", t.attack_speed(), Fonts::Regular, 24., Color::WHITE))
                     .add_child(LabeledValue::new("Range", t.range(), Fonts::Regular, 24., Color::WHITE));
-                self.indicator = Some(state.entities.create_at(crate::gust::tower::Indicator::new(t.range()), e.position));
+                self.indicator = Some(state.entities.create_child_at(entities[0], crate::gust::tower::Indicator::new(t.range()), crate::V2::new(0., 0.)));
             }
         }
 
@@ -127,33 +136,24 @@ impl SceneBehavior for Level {
 }
 
 impl MessageHandler for Level {
-    crate::handle_messages!(Messages::EnemyGotToEnd(0), Messages::EnemyKilled);
+    crate::handle_messages!(EnemyGotToEnd, EnemyKilled);
     
-    fn process(&mut self, message: &Messages, context: &mut MessageContext) { 
-        match message {
-            Messages::EnemyGotToEnd(damage) => {
-                let h = context.root.find_mut::<LabeledValue<u32>>("health").unwrap();
-                let current = h.value();
-                let damage = u32::min(*damage, current);
-                h.set_value(current - damage);
-
-                // This code needs to run unconditionally
-                let level_done = self.spawner.mark_enemy_dead();
-                if h.value() == 0 {
-                    self.status = LevelStatus::Fail
-                } else if level_done {
-                    self.status = LevelStatus::Success
-                }
-            },
-            Messages::EnemyKilled => {
-                self.gold += 10;
-                if let Some(g) = context.root.find_mut::<LabeledValue<u32>>("gold") {
-                    g.set_value(self.gold);
-                }
-                if self.spawner.mark_enemy_dead() {
-                    self.status = LevelStatus::Success
-                }
+    fn process(&mut self, message: &Message, context: &mut MessageContext) { 
+        if let Some(EnemyGotToEnd(damage)) = message.get() {
+            let h = context.root.find_mut::<LabeledValue<u32>>("health").unwrap();
+            let current = h.value();
+            let damage = u32::min(*damage, current);
+            h.set_value(current - damage);
+
+            // This code needs to run unconditionally
+            let level_done = self.spawner.mark_enemy_dead();
+            if h.value() == 0 {
+                self.status = LevelStatus::Fail
+            } else if level_done {
+                self.status = LevelStatus::Success
             }
+        } else if message.is::<EnemyKilled>() && self.spawner.mark_enemy_dead() {
+            self.status = LevelStatus::Success
         }
     }
 }
@@ -1,6 +1,6 @@
 use crate::V2;
-use crate::entity::{Entity, EntityInitialization, EntityUpdate, EntityHandle};
-use crate::messages::{MessageHandler, Messages, MessageContext};
+use crate::entity::{Entity, EntityInitialization, EntityUpdate, EntityHandle, Prefab};
+use crate::messages::{MessageHandler, Message, MessageContext};
 use crate::utils::Timer;
 use cgmath::MetricSpace;
 
@@ -17,6 +17,9 @@ pub struct Tower {
     target: Option<EntityHandle>,
 }
 impl Tower {
+    pub fn from_prefab(prefab: &Prefab) -> Tower {
+        Self::new(prefab.parameter("attack_speed"), prefab.parameter("damage"), prefab.parameter("range"))
+    }
     pub fn new(attack_speed: f32, damage: f32, range: f32) -> Tower {
         Tower {
             timer: Timer::new(1. / attack_speed),
@@ -36,6 +39,12 @@ impl Tower {
     pub fn range(&self) -> f32 { self.range }
     pub fn set_range(&mut self, range: f32) { self.range = range; }
 
+    /// Towers can't shoot through the player
+    fn in_sight(position: V2, target: V2) -> bool {
+        let layers = crate::gust::PhysicsLayers::Player.into();
+        crate::physics::raycast(position, target - position, position.distance(target), layers).is_none()
+    }
+
     fn find_target(&mut self, position: V2, manager: &crate::entity::EntityManager, scene: &crate::physics::QuadTree) {
         if self.target.is_some() {
             match manager.get(&self.target.unwrap()) {
@@ -70,7 +79,9 @@ impl crate::entity::EntityBehavior for Tower {
             self.find_target(e.position(), state.entities, state.quad_tree);
             if let Some(t) = self.target {
                 let target = state.entities.get(&t).unwrap().position;
-                state.entities.create_at(bullet::Bullet::fire(&state.queue, 500., self.damage, target), e.position());
+                let damage = self.damage;
+                if !Self::in_sight(e.position(), target) { return; }
+                state.commands().spawn_prefab_with("bullet", e.position(), move |b: &mut Bullet| b.fire(damage, target));
             }
 
         }
@@ -81,5 +92,5 @@ impl crate::entity::EntityBehavior for Tower {
 }
 impl MessageHandler for Tower {
     crate::handle_messages!();
-    fn process(&mut self, _message: &Messages, _context: &mut MessageContext) {}
+    fn process(&mut self, _message: &Message, _context: &mut MessageContext) {}
 }
\ No newline at end of file
@@ -1,7 +1,7 @@
-use crate::entity::{SceneBehavior, SceneLoad, EntityManager};
-use crate::assets::{Sound, Sounds, SoundHandle, SoundStatus, Fonts};
+use crate::entity::{SceneBehavior, SceneLoad, EntityManager, Transition};
+use crate::assets::{Sound, Sounds, SoundHandle, SoundStatus, Fonts, AssetManifest};
 use crate::job_system::ThreadSafeJobQueue;
-use crate::messages::{Messages, MessageHandler, MessageContext};
+use crate::messages::{Message, MessageHandler, MessageContext};
 use crate::ui::{Label, DockContainer};
 use crate::input::Actions;
 use crate::graphics::Color;
@@ -35,6 +35,12 @@ impl MainMenu {
     }
 }
 impl SceneBehavior for MainMenu {
+    fn assets(&self) -> AssetManifest {
+        AssetManifest::new()
+            .font(Fonts::Regular)
+            .sound(Sounds::Shoot)
+    }
+
     fn load(&mut self, queue: ThreadSafeJobQueue, _entities: &mut EntityManager, root: &mut crate::ui::Widget) {
         root.with_child(DockContainer::vertical(0.5, 0.5, None))
             .add_named_child(Label::new(String::from("New Game"), Fonts::Regular, 64., Color::RED), self.labels[0])
@@ -60,7 +66,7 @@ impl SceneBehavior for MainMenu {
 
         if state.action_pressed(Actions::Accept) {
             match self.selected_index {
-                0 => return SceneLoad::Load(Box::new(crate::gust::level::Level::new())),
+                0 => return SceneLoad::Transition(Box::new(crate::gust::level::Level::new()), Transition::fade(0.5)),
                 1 => return SceneLoad::None,
                 2 => return SceneLoad::Unload,
                 _ => panic!("Invalid selection index"),
@@ -73,5 +79,5 @@ impl SceneBehavior for MainMenu {
 }
 impl MessageHandler for MainMenu {
     crate::handle_messages!();
-    fn process(&mut self, _message: &Messages, _context: &mut MessageContext) {}
+    fn process(&mut self, _message: &Message, _context: &mut MessageContext) {}
 }
\ No newline at end of file
@@ -0,0 +1,3 @@
+/// An enemy reached the end of the path, dealing this much damage
+pub struct EnemyGotToEnd(pub u32);
+pub struct EnemyKilled;
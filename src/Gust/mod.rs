@@ -4,12 +4,23 @@ mod level;
 mod tower;
 mod cards;
 pub mod main_menu;
+pub mod pause_menu;
 pub mod game_data;
+pub mod messages;
 
 crate::physics_layer_enum! {
+    /// Layers are given by name in the prefab file
     pub enum PhysicsLayers {
         Enemy = 1,
         Player = 2,
         Bullet = 4,
     }
+}
+
+/// Behaviors that can be created from the prefab file
+pub fn register_prefabs(prefabs: &mut crate::entity::PrefabLibrary) {
+    prefabs.register_layers(PhysicsLayers::LAYERS);
+    prefabs.register("Enemy", |p| Box::new(enemy::Enemy::from_prefab(p)));
+    prefabs.register("Tower", |p| Box::new(tower::Tower::from_prefab(p)));
+    prefabs.register("Bullet", |p| Box::new(tower::Bullet::from_prefab(p)));
 }
\ No newline at end of file
@@ -0,0 +1,73 @@
+use crate::entity::{SceneBehavior, SceneLoad, EntityManager};
+use crate::assets::Fonts;
+use crate::job_system::ThreadSafeJobQueue;
+use crate::messages::{Message, MessageHandler, MessageContext};
+use crate::ui::{Label, DockContainer};
+use crate::input::Actions;
+use crate::graphics::Color;
+
+pub struct PauseMenu {
+    selected_index: usize,
+    labels: [&'static str; 2]
+}
+impl PauseMenu {
+    pub fn new() -> PauseMenu {
+        PauseMenu { 
+            selected_index: 0,
+            labels: ["resume", "quit"],
+        }
+    }
+
+    fn move_index(&mut self, up: bool, root: &mut crate::ui::Widget) {
+        let l = root.find_mut::<Label>(self.labels[self.selected_index]).unwrap();
+        l.set_color(Color::WHITE);
+
+        if up {
+            self.selected_index -= 1;
+        } else {
+            self.selected_index += 1;
+        }
+
+        let l = root.find_mut::<Label>(self.labels[self.selected_index]).unwrap();
+        l.set_color(Color::RED);
+    }
+}
+impl SceneBehavior for PauseMenu {
+    fn load(&mut self, _queue: ThreadSafeJobQueue, _entities: &mut EntityManager, root: &mut crate::ui::Widget) {
+        root.with_child(DockContainer::vertical(0.5, 0.5, Some(Color::GRAY)))
+            .add_named_child(Label::new(String::from("Resume"), Fonts::Regular, 64., Color::RED), self.labels[0])
+            .add_named_child(Label::new(String::from("Quit"), Fonts::Regular, 64., Color::WHITE), self.labels[1]);
+    }
+
+    fn unload(&mut self) { }
+
+    fn update(&mut self, state: &mut crate::UpdateState, root: &mut crate::ui::Widget) -> SceneLoad {
+        if state.action_pressed(Actions::Cancel) {
+            return SceneLoad::Pop;
+        }
+
+        if state.action_pressed(Actions::Up) && self.selected_index > 0 { 
+            self.move_index(true, root);
+        }
+        if state.action_pressed(Actions::Down) && self.selected_index < self.labels.len() - 1 { 
+            self.move_index(false, root);
+        }
+
+        if state.action_pressed(Actions::Accept) {
+            match self.selected_index {
+                0 => return SceneLoad::Pop,
+                1 => return SceneLoad::Load(Box::new(crate::gust::main_menu::MainMenu::new())),
+                _ => panic!("Invalid selection index"),
+            }
+        }
+        SceneLoad::None
+    }
+
+    fn render(&self, _graphics: &mut crate::Graphics) { }
+
+    fn render_below(&self) -> bool { true }
+}
+impl MessageHandler for PauseMenu {
+    crate::handle_messages!();
+    fn process(&mut self, _message: &Message, _context: &mut MessageContext) {}
+}
@@ -2,7 +2,7 @@ use crate::input::Actions;
 use crate::V2;
 use crate::entity::{Entity, EntityInitialization, EntityUpdate};
 use crate::physics::{PhysicsMaterial, Circle, CollisionShape};
-use crate::messages::{MessageHandler, Messages, MessageContext};
+use crate::messages::{MessageHandler, Message, MessageContext};
 use crate::gust::PhysicsLayers;
 
 pub struct Player {
@@ -43,5 +43,5 @@ impl crate::entity::EntityBehavior for Player {
 }
 impl MessageHandler for Player {
     crate::handle_messages!();
-    fn process(&mut self, _message: &Messages, _context: &mut MessageContext) {}
+    fn process(&mut self, _message: &Message, _context: &mut MessageContext) {}
 }
\ No newline at end of file
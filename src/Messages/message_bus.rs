@@ -1,69 +1,226 @@
-use super::{Messages, MessageHandler, MessageContext};
-use std::collections::VecDeque;
+use super::{Message, MessageHandler, MessageContext, MessageTracer, TraceEvent};
+use crate::generational_array::{GenerationalArray, GenerationalIndex};
+use crate::entity::{EntityHandle, SceneId};
+use crate::ui::WidgetId;
+use std::collections::{HashMap, VecDeque};
+use std::any::TypeId;
 
 pub type SharedMessageBus = std::rc::Rc<std::cell::RefCell<MessageBus>>;
+pub type SubscriptionHandle = GenerationalIndex;
+
+/// Owner of a subscription, handlers are looked up through this when a message is delivered
+/// so a handler that no longer exists is skipped instead of being dereferenced
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Subscriber {
+    Entity(EntityHandle),
+    Scene(SceneId),
+    Widget(WidgetId),
+}
+
+/// Who a message is delivered to
+#[derive(Clone, PartialEq, Debug)]
+pub enum Target {
+    /// Every handler subscribed to the message type
+    All,
+    /// Only this entity, whether or not it subscribed to the message type
+    Entity(EntityHandle),
+    /// Only the widget with this name
+    Widget(&'static str),
+}
+
+enum Due {
+    Time(f32),
+    Frame(u64),
+}
+struct DelayedMessage {
+    due: Due,
+    message: Message,
+}
+
+struct Subscription {
+    message: TypeId,
+    subscriber: Subscriber,
+}
+
 pub struct MessageBus {
-    messages: VecDeque<Messages>,
-    recipients: [Option<Vec<*mut dyn MessageHandler>>; 256]
+    messages: VecDeque<Message>,
+    delayed: Vec<DelayedMessage>,
+    /// Seconds and updates since the bus was created
+    time: f32,
+    frame: u64,
+    subscriptions: GenerationalArray<Subscription, 64>,
+    recipients: HashMap<TypeId, Vec<SubscriptionHandle>>,
+    tracer: Option<MessageTracer>,
 }
 impl MessageBus {
     pub fn new() -> MessageBus {    
         MessageBus {
             messages: VecDeque::new(),
-            recipients: crate::utils::init_optional_array_to_blank(),
+            delayed: vec!(),
+            time: 0.,
+            frame: 0,
+            subscriptions: GenerationalArray::new(),
+            recipients: HashMap::new(),
+            tracer: None,
         }
     }
-    pub fn send(&mut self, message: Messages) {
+    pub fn send<T: 'static>(&mut self, message: T) {
+        self.send_to(Target::All, message);
+    }
+    pub fn send_to<T: 'static>(&mut self, target: Target, message: T) {
+        let message = Message::new(message, target);
+        self.trace(&message, TraceEvent::Sent(message.target().clone()));
         self.messages.push_back(message);
     }
+    /// Delivers the message once `seconds` of game time have passed
+    pub fn send_delayed<T: 'static>(&mut self, target: Target, message: T, seconds: f32) {
+        self.schedule(Due::Time(self.time + seconds), Message::new(message, target));
+    }
+    /// Delivers the message once `frame` updates have passed, see `MessageBus::frame`
+    pub fn send_at_frame<T: 'static>(&mut self, target: Target, message: T, frame: u64) {
+        self.schedule(Due::Frame(frame), Message::new(message, target));
+    }
+    fn schedule(&mut self, due: Due, message: Message) {
+        self.trace(&message, TraceEvent::Scheduled(message.target().clone()));
+        self.delayed.push(DelayedMessage { due, message });
+    }
 
-    pub fn process_messages(&mut self, context: &mut MessageContext) {
-        while let Some(m) = self.messages.pop_front() {
-            let kind = super::raw_kind(&m);
+    /// Records every message sent and delivered from now on, `None` turns tracing off
+    pub fn set_tracer(&mut self, tracer: Option<MessageTracer>) {
+        self.tracer = tracer;
+    }
+    pub fn tracer(&self) -> Option<&MessageTracer> {
+        self.tracer.as_ref()
+    }
+    fn trace(&mut self, message: &Message, event: TraceEvent) {
+        if let Some(t) = &mut self.tracer {
+            t.record(self.frame, message, event);
+        }
+    }
+
+    /// Drops every queued or delayed message sent to `target`
+    pub fn cancel(&mut self, target: &Target) {
+        self.messages.retain(|m| m.target() != target);
+        self.delayed.retain(|d| d.message.target() != target);
+    }
+
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
 
-            if let Some(recipients) = &mut self.recipients[kind as usize] {
-                for r in &mut recipients.iter() {
-                    let handler: &mut dyn MessageHandler = unsafe { (*r).as_mut().expect("Message handler was not property remove from message bus") };
-                    handler.process(&m, context);
-                }
+    /// Moves the bus forward by one update, delayed messages that are due are queued in the order they were sent
+    pub fn advance(&mut self, delta_time: f32) {
+        self.time += delta_time;
+        self.frame += 1;
+        if let Some(t) = &mut self.tracer {
+            t.flush();
+        }
+
+        let (time, frame) = (self.time, self.frame);
+        let mut i = 0;
+        while i < self.delayed.len() {
+            let due = match self.delayed[i].due {
+                Due::Time(t) => t <= time,
+                Due::Frame(f) => f <= frame,
+            };
+            if due {
+                let d = self.delayed.remove(i);
+                self.messages.push_back(d.message);
+            } else {
+                i += 1;
             }
         }
     }
 
-    pub fn register(&mut self, handler: &mut (impl MessageHandler + 'static), kind: u8) {
-        if self.recipients[kind as usize].is_none() {
-            self.recipients[kind as usize] = Some(vec!());
+    /// Delivers every queued message, `scenes` are the scene handlers that can receive messages
+    pub fn process_messages(&mut self, scenes: &mut [(SceneId, &mut dyn MessageHandler)], context: &mut MessageContext) {
+        while let Some(m) = self.messages.pop_front() {
+            self.trace(&m, TraceEvent::Dispatched);
+            match m.target() {
+                Target::All => self.broadcast(&m, scenes, context),
+                // Messages to something that was destroyed are dropped
+                Target::Entity(h) => {
+                    if context.entities.get(h).is_some() {
+                        self.trace(&m, TraceEvent::Handled(Subscriber::Entity(*h)));
+                        crate::entity::process_message(*h, &m, context);
+                    } else {
+                        self.trace(&m, TraceEvent::Dropped(m.target().clone()));
+                    }
+                },
+                Target::Widget(name) => {
+                    if let Some(widget) = context.root.find_widget_mut(name) {
+                        self.trace(&m, TraceEvent::Handled(Subscriber::Widget(widget.id())));
+                        widget.process_message(&m);
+                    } else {
+                        self.trace(&m, TraceEvent::Dropped(m.target().clone()));
+                    }
+                },
+            }
         }
-        let recipients = &mut self.recipients[kind as usize].as_mut().unwrap();
-        recipients.push(handler as *mut dyn MessageHandler);
     }
 
-    pub fn unregister(&mut self, handler: &impl MessageHandler, kind: u8) {
-        if let Some(recipients) = &mut self.recipients[kind as usize] {
-            MessageBus::_unregister(handler, recipients);
+    fn broadcast(&mut self, m: &Message, scenes: &mut [(SceneId, &mut dyn MessageHandler)], context: &mut MessageContext) {
+        let recipients = match self.recipients.get(&m.type_id()) {
+            Some(r) => r.clone(),
+            None => return,
+        };
+
+        for r in recipients {
+            // Handlers can unsubscribe while this message is being delivered
+            let subscriber = match self.subscriptions.get(&r) {
+                Some(s) => s.subscriber.clone(),
+                None => continue,
+            };
+
+            match subscriber {
+                Subscriber::Entity(h) => {
+                    if context.entities.get(&h).is_some() {
+                        self.trace(m, TraceEvent::Handled(subscriber));
+                        crate::entity::process_message(h, m, context);
+                    }
+                },
+                Subscriber::Scene(id) => {
+                    if let Some((_, handler)) = scenes.iter_mut().find(|(s, _)| *s == id) {
+                        self.trace(m, TraceEvent::Handled(subscriber));
+                        handler.process(m, context);
+                    }
+                },
+                Subscriber::Widget(id) => {
+                    if let Some(widget) = context.root.find_id_mut(id) {
+                        self.trace(m, TraceEvent::Handled(subscriber));
+                        widget.process_message(m);
+                    }
+                },
+            }
         }
     }
 
-    pub fn unregister_all(&mut self, handler: &impl MessageHandler) {
-        for handlers in &mut self.recipients.iter_mut().flatten() {
-            MessageBus::_unregister(handler, handlers);
-        }
+    pub fn subscribe<T: 'static>(&mut self, subscriber: Subscriber) -> SubscriptionHandle {
+        let message = TypeId::of::<T>();
+        let (handle, _) = self.subscriptions.push(Subscription { message, subscriber });
+        self.recipients.entry(message).or_default().push(handle);
+        handle
     }
 
-    fn _unregister(handler: &impl MessageHandler, handlers: &mut Vec<*mut dyn MessageHandler>) {
-        let mut remove = None;
-        for (i, r) in handlers.iter().enumerate() {
-            let r = unsafe { (*r).as_ref().unwrap() };
-            if std::ptr::eq(r, handler) {
-                remove = Some(i);
-            }
+    pub fn unsubscribe(&mut self, handle: SubscriptionHandle) -> bool {
+        let message = match self.subscriptions.get(&handle) {
+            Some(s) => s.message,
+            None => return false,
+        };
+        if let Some(recipients) = self.recipients.get_mut(&message) {
+            recipients.retain(|r| *r != handle);
         }
+        self.subscriptions.remove(&handle)
+    }
 
-        if let Some(i) = remove {
-            handlers.remove(i);
+    pub fn unsubscribe_all(&mut self, subscriber: &Subscriber) {
+        for h in self.subscriptions.iter_index() {
+            if self.subscriptions.get(&h).unwrap().subscriber == *subscriber {
+                self.unsubscribe(h);
+            }
         }
     }
 }
 impl Default for MessageBus {
     fn default() -> Self { Self::new() }
-}
\ No newline at end of file
+}
@@ -1,42 +1,59 @@
 mod message_bus;
-pub use message_bus::{MessageBus, SharedMessageBus};
+mod tracer;
+pub use message_bus::{MessageBus, SharedMessageBus, Subscriber, Target};
+pub use tracer::{MessageTracer, TraceEvent};
+use std::any::{Any, TypeId};
 
-pub enum Messages {
-    EnemyGotToEnd(u32),
-    EnemyKilled,
+/// A message waiting to be delivered, messages can be any `'static` type defined by the game
+pub struct Message {
+    payload: Box<dyn Any>,
+    name: &'static str,
+    target: Target,
+}
+impl Message {
+    pub fn new<T: 'static>(payload: T, target: Target) -> Message {
+        Message { payload: Box::new(payload), name: std::any::type_name::<T>(), target }
+    }
+    pub fn get<T: 'static>(&self) -> Option<&T> {
+        self.payload.downcast_ref::<T>()
+    }
+    pub fn is<T: 'static>(&self) -> bool {
+        self.payload.is::<T>()
+    }
+    pub fn type_id(&self) -> TypeId {
+        self.payload.as_ref().type_id()
+    }
+    pub fn target(&self) -> &Target {
+        &self.target
+    }
+    /// Name of the message type, only meant for debugging
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
 }
-
 
 pub trait MessageHandler {
-    fn process(&mut self, message: &Messages, context: &mut MessageContext);
-    fn register(&mut self, messages: &mut MessageBus);
-    fn unregister(&self, messages: &mut MessageBus);
+    fn process(&mut self, message: &Message, context: &mut MessageContext);
+    /// Subscribes to every message type this handler processes
+    fn register(&self, messages: &mut MessageBus, subscriber: Subscriber);
 }
 
+/// Implements `MessageHandler::register` for a list of message types
+/// ```
+/// crate::handle_messages!(EnemyKilled, EnemyGotToEnd);
+/// ```
 #[macro_export]
 macro_rules! handle_messages {
-    ($($message:expr),*) => { 
-        fn register(&mut self, _messages: &mut $crate::messages::MessageBus) {
+    ($($message:ty),*) => { 
+        fn register(&self, _messages: &mut $crate::messages::MessageBus, _subscriber: $crate::messages::Subscriber) {
             $(
-                let kind = $crate::messages::raw_kind(&$message);
-                _messages.register(self, kind);
+                _messages.subscribe::<$message>(_subscriber.clone());
             )*
         }
-        fn unregister(&self, messages: &mut $crate::messages::MessageBus) {
-            messages.unregister_all(self);
-        }
     }
 }
 
-pub fn raw_kind(kind: &Messages) -> u8 {
-    //SUUUUPER janky code to get the hidden tag field behind enums
-    let parts = unsafe {
-        core::slice::from_raw_parts(kind as *const _ as *const u8, std::mem::size_of_val(kind))
-    };
-    parts[0]
-}
-
 pub struct MessageContext<'a> {
     pub root: &'a mut crate::ui::Widget,
     pub entities: &'a mut crate::entity::EntityManager
-}
\ No newline at end of file
+}
@@ -0,0 +1,143 @@
+use super::{Message, Subscriber, Target};
+use crate::logger::info;
+use crate::graphics::{Graphics, Color};
+use crate::assets::Fonts;
+use crate::V2;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+const OVERLAY_FONT_SIZE: f32 = 14.;
+
+#[derive(Clone, Debug)]
+pub enum TraceEvent {
+    /// Queued for the next time messages are processed
+    Sent(Target),
+    /// Queued for a later time or frame
+    Scheduled(Target),
+    /// Taken off the queue to be delivered
+    Dispatched,
+    /// Delivered to a handler
+    Handled(Subscriber),
+    /// The target no longer exists
+    Dropped(Target),
+}
+
+impl std::fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TraceEvent::Sent(target) => write!(f, "sent to {:?}", target),
+            TraceEvent::Scheduled(target) => write!(f, "scheduled for {:?}", target),
+            TraceEvent::Dispatched => write!(f, "dispatched"),
+            TraceEvent::Handled(subscriber) => write!(f, "handled by {:?}", subscriber),
+            TraceEvent::Dropped(target) => write!(f, "dropped, {:?} no longer exists", target),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TraceRecord {
+    pub frame: u64,
+    pub message: &'static str,
+    pub event: TraceEvent,
+}
+impl std::fmt::Display for TraceRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {} {}", self.frame, self.message, self.event)
+    }
+}
+
+enum TraceOutput {
+    None,
+    Log,
+    File(BufWriter<File>),
+}
+
+/// Records what happens to every message on the bus, see `MessageBus::set_tracer`
+/// The most recent records are kept in memory, every record can also be written to the log or a file as it happens
+pub struct MessageTracer {
+    records: VecDeque<TraceRecord>,
+    capacity: usize,
+    output: TraceOutput,
+}
+impl MessageTracer {
+    /// Keeps the last `capacity` records in memory, 0 keeps none so records only go to the log or file
+    pub fn new(capacity: usize) -> MessageTracer {
+        MessageTracer { records: VecDeque::with_capacity(capacity), capacity, output: TraceOutput::None }
+    }
+
+    /// Writes every record to the log
+    pub fn with_log(mut self) -> Self {
+        self.output = TraceOutput::Log;
+        self
+    }
+
+    /// Writes every record to a file, the file is truncated if it exists
+    pub fn with_file<P: AsRef<Path>>(mut self, path: P) -> std::io::Result<Self> {
+        self.output = TraceOutput::File(BufWriter::new(File::create(path)?));
+        Ok(self)
+    }
+
+    pub(super) fn record(&mut self, frame: u64, message: &Message, event: TraceEvent) {
+        let record = TraceRecord { frame, message: message.name(), event };
+        match &mut self.output {
+            TraceOutput::None => {},
+            TraceOutput::Log => info!("{}", record),
+            // Tracing is only for debugging, a failed write shouldn't stop the game
+            TraceOutput::File(file) => { let _ = writeln!(file, "{}", record); },
+        }
+
+        if self.capacity == 0 {
+            return;
+        }
+        if self.records.len() == self.capacity {
+            self.records.pop_front();
+        }
+        self.records.push_back(record);
+    }
+
+    /// Writes out anything recorded this frame so the file is complete if the game stops
+    pub(super) fn flush(&mut self) {
+        if let TraceOutput::File(file) = &mut self.output {
+            let _ = file.flush();
+        }
+    }
+
+    /// Draws the last `count` records in the top left of the screen
+    pub fn render_overlay(&self, graphics: &mut Graphics, count: usize) {
+        let skip = self.records.len().saturating_sub(count);
+        for (i, r) in self.records.iter().skip(skip).enumerate() {
+            let position = V2::new(4., 4. + i as f32 * OVERLAY_FONT_SIZE);
+            graphics.draw_text_simple(position, Fonts::Regular, OVERLAY_FONT_SIZE, Color::WHITE, &r.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Ping;
+
+    fn record(tracer: &mut MessageTracer, count: u64) {
+        let message = Message::new(Ping, Target::All);
+        for frame in 0..count {
+            tracer.record(frame, &message, TraceEvent::Dispatched);
+        }
+    }
+
+    #[test]
+    fn keeps_the_most_recent_records() {
+        let mut tracer = MessageTracer::new(3);
+        record(&mut tracer, 5);
+        assert_eq!(tracer.records.iter().map(|r| r.frame).collect::<Vec<_>>(), vec!(2, 3, 4));
+    }
+
+    #[test]
+    fn zero_capacity_keeps_nothing() {
+        let mut tracer = MessageTracer::new(0);
+        record(&mut tracer, 5);
+        assert!(tracer.records.is_empty());
+    }
+}
@@ -0,0 +1,95 @@
+use super::{RigidBody, RigidBodyHandle, BodyType};
+use crate::generational_array::GenerationalArray;
+use crate::entity::ENTITY_CHUNK_SIZE;
+use crate::utils::Rectangle;
+
+struct Proxy {
+    body: RigidBodyHandle,
+    bounds: Rectangle,
+}
+
+/// Sweep and prune along the x axis
+/// Bodies are kept sorted by the left edge of their bounds from the previous step,
+/// since bodies move a small amount each step re-sorting is close to linear
+pub(super) struct Broadphase {
+    proxies: Vec<Proxy>,
+}
+impl Broadphase {
+    pub fn new() -> Broadphase {
+        Broadphase { proxies: vec!() }
+    }
+
+    pub fn insert(&mut self, body: RigidBodyHandle) {
+        let bounds = Rectangle::new(crate::V2::new(0., 0.), crate::V2::new(0., 0.));
+        self.proxies.push(Proxy { body, bounds });
+    }
+
+    pub fn remove(&mut self, body: &RigidBodyHandle) {
+        self.proxies.retain(|p| p.body != *body);
+    }
+
+    /// Moves every proxy to where its body is now and restores the sort order
+    unsafe fn update(&mut self, bodies: &GenerationalArray<RigidBody, ENTITY_CHUNK_SIZE>) {
+        for p in &mut self.proxies {
+            if let Some(b) = bodies.get(&p.body) {
+                p.bounds = b.shape.bounds((*b.entity).position);
+            }
+        }
+
+        // Insertion sort, nearly sorted from the previous step
+        for i in 1..self.proxies.len() {
+            let mut j = i;
+            while j > 0 && self.proxies[j - 1].bounds.left() > self.proxies[j].bounds.left() {
+                self.proxies.swap(j - 1, j);
+                j -= 1;
+            }
+        }
+    }
+
+    /// Every body whose bounds overlap `bounds`
+    pub unsafe fn query(&mut self, bodies: &GenerationalArray<RigidBody, ENTITY_CHUNK_SIZE>, bounds: &Rectangle) -> Vec<RigidBodyHandle> {
+        self.update(bodies);
+        self.proxies.iter()
+            // Everything after this starts to the right of the bounds
+            .take_while(|p| p.bounds.left() <= bounds.right())
+            .filter(|p| bounds.intersect(&p.bounds).is_some())
+            .map(|p| p.body)
+            .collect()
+    }
+
+    /// Every pair of bodies that overlap and can collide
+    pub unsafe fn pairs(&mut self, bodies: &GenerationalArray<RigidBody, ENTITY_CHUNK_SIZE>) -> Vec<(RigidBodyHandle, RigidBodyHandle)> {
+        self.update(bodies);
+
+        let mut pairs = vec!();
+        for (i, a) in self.proxies.iter().enumerate() {
+            let body_a = match bodies.get(&a.body) {
+                Some(b) => b,
+                None => continue,
+            };
+
+            for b in &self.proxies[i + 1..] {
+                // Everything after this starts to the right of a
+                if b.bounds.left() > a.bounds.right() { break; }
+                if b.bounds.top() > a.bounds.bottom() || b.bounds.bottom() < a.bounds.top() { continue; }
+
+                if let Some(body_b) = bodies.get(&b.body) && can_collide(body_a, body_b) {
+                    pairs.push((a.body, b.body));
+                }
+            }
+        }
+        pairs
+    }
+}
+
+/// Bodies collide if either one collides with the other's layer
+pub(super) unsafe fn can_collide(a: &RigidBody, b: &RigidBody) -> bool {
+    // Neither object can be moved by the other, no collision will occur. Sensors still overlap
+    if !a.sensor && !b.sensor && a.body_type != BodyType::Dynamic && b.body_type != BodyType::Dynamic { return false; }
+    // Dynamic bodies can still have no mass, there is nothing for the solver to resolve
+    if !a.sensor && !b.sensor && a.inverse_mass + b.inverse_mass == 0. { return false; }
+    // Objects belong to a scene that isn't updating
+    if a.is_paused() || b.is_paused() { return false; }
+
+    a.colliding_layers & b.layer != 0 || b.colliding_layers & a.layer != 0
+}
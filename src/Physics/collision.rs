@@ -1,11 +1,10 @@
 use crate::V2;
-use crate::entity::Entity;
 use crate::physics::{Polygon, Circle, Manifold};
 use cgmath::{InnerSpace, Matrix, MetricSpace};
 
-pub fn circle_to_circle(manifold: &mut Manifold, entity_a: &Entity, entity_b: &Entity, a: &Circle, b: &Circle) {
+pub fn circle_to_circle(manifold: &mut Manifold, position_a: V2, position_b: V2, a: &Circle, b: &Circle) {
 	// Calculate translational vector, which is normal
-	let normal = entity_b.position - entity_a.position;
+	let normal = position_b - position_a;
 
 	let dist_sqr = normal.magnitude2();
 	let radius = a.radius() + b.radius();
@@ -22,20 +21,20 @@ pub fn circle_to_circle(manifold: &mut Manifold, entity_a: &Entity, entity_b: &E
 	if distance == 0. {
 		manifold.penetration = a.radius();
 		manifold.normal = V2::new(1., 0.);
-		manifold.contacts[0] = entity_a.position;
+		manifold.contacts[0] = position_a;
 	} else {
 		manifold.penetration = radius - distance;
 		manifold.normal = normal / distance; // Faster than using Normalized since we already performed sqrt
-		manifold.contacts[0] = manifold.normal * a.radius() + entity_a.position;
+		manifold.contacts[0] = manifold.normal * a.radius() + position_a;
 	}
 }
 
-pub fn circle_to_polygon(manifold: &mut Manifold, entity_a: &Entity, entity_b: &Entity, a: &Circle, b: &Polygon) {
+pub fn circle_to_polygon(manifold: &mut Manifold, position_a: V2, position_b: V2, a: &Circle, b: &Polygon) {
 	manifold.contact_count = 0;
 
 	// Transform circle center to Polygon model space
-	let center = entity_a.position;
-	let center = b.u.transpose() * (center - entity_b.position);
+	let center = position_a;
+	let center = b.u.transpose() * (center - position_b);
 
     let vertices = b.vertices();
     let normals = b.normals();
@@ -62,7 +61,7 @@ pub fn circle_to_polygon(manifold: &mut Manifold, entity_a: &Entity, entity_b: &
 	if separation < f32::EPSILON {
 		manifold.contact_count = 1;
 		manifold.normal = (b.u * normals[face_normal]) * -1.;
-		manifold.contacts[0] = manifold.normal * a.radius() + entity_a.position;
+		manifold.contacts[0] = manifold.normal * a.radius() + position_a;
 		manifold.penetration = a.radius();
 		return;
 	}
@@ -80,7 +79,7 @@ pub fn circle_to_polygon(manifold: &mut Manifold, entity_a: &Entity, entity_b: &
 		let n = v1 - center;
 		let n = b.u * n;
 		manifold.normal = n.normalize();
-		v1 = b.u * v1 + entity_b.position;
+		v1 = b.u * v1 + position_b;
 		manifold.contacts[0] = v1;
 	} else if dot2 <= 0. { 
         // Closest to v2
@@ -90,7 +89,7 @@ pub fn circle_to_polygon(manifold: &mut Manifold, entity_a: &Entity, entity_b: &
 		let n = v2 - center;
 		let n = b.u * n;
 		manifold.normal = n.normalize();
-		v2 = b.u * v2 + entity_b.position;
+		v2 = b.u * v2 + position_b;
 		manifold.contacts[0] = v2;
 	} else  {
         // Closest to face
@@ -99,7 +98,7 @@ pub fn circle_to_polygon(manifold: &mut Manifold, entity_a: &Entity, entity_b: &
         
 		let n = b.u * n;
 		manifold.normal = n * -1.;
-		manifold.contacts[0] = manifold.normal * a.radius() + entity_a.position;
+		manifold.contacts[0] = manifold.normal * a.radius() + position_a;
 		manifold.contact_count = 1;
 	}
 }
@@ -204,24 +203,24 @@ fn bias_greater_than(a: f32, b: f32) -> bool {
 	a >= b * 0.95 + a * 0.01
 }
 
-pub fn polygon_to_polygon(manifold: &mut Manifold, entity_a: &Entity, entity_b: &Entity, a: &Polygon, b: &Polygon) {
+pub fn polygon_to_polygon(manifold: &mut Manifold, position_a: V2, position_b: V2, a: &Polygon, b: &Polygon) {
 	manifold.contact_count = 0;
 
 	// Check for a separating axis with A's face planes
-	let (penetration_a, face_a) = find_axis_least_penetration(entity_a.position, entity_b.position, a, b);
+	let (penetration_a, face_a) = find_axis_least_penetration(position_a, position_b, a, b);
 	if penetration_a >= 0. { return; }
 
 	// Check for a separating axis with B's face planes
-	let (penetration_b, face_b) = find_axis_least_penetration(entity_b.position, entity_a.position, b, a);
+	let (penetration_b, face_b) = find_axis_least_penetration(position_b, position_a, b, a);
 	if penetration_b >= 0. { return; }
 
 
 	// Determine which shape contains reference face
 	let (ref_poly, inc_poly, ref_pos, inc_pos, reference_index, flip) = 
     if bias_greater_than(penetration_a, penetration_b) {
-		(a, b, entity_a.position, entity_b.position, face_a, false)
+		(a, b, position_a, position_b, face_a, false)
 	} else {
-        (b, a, entity_b.position, entity_a.position, face_b, true)
+        (b, a, position_b, position_a, face_b, true)
 	};
 
 	// World space incident face
@@ -1,6 +1,7 @@
 #![allow(dead_code)]
 
 use crate::V2;
+use crate::utils::Rectangle;
 use cgmath::{Matrix2, InnerSpace, SquareMatrix};
 use std::f32::EPSILON;
 
@@ -19,6 +20,26 @@ impl CollisionShape {
             CollisionShape::Polygon(p) => p.set_orient(rotation),
         }
     }
+
+    /// Axis aligned bounds of the shape for a body at `position`
+    pub fn bounds(&self, position: V2) -> Rectangle {
+        match self {
+            CollisionShape::Circle(c) => {
+                let r = V2::new(c.radius, c.radius);
+                Rectangle::from_coords(position - r, position + r)
+            },
+            CollisionShape::Polygon(p) => {
+                let mut min = V2::new(f32::MAX, f32::MAX);
+                let mut max = V2::new(f32::MIN, f32::MIN);
+                for v in &p.vertices {
+                    let v = p.u * *v + position;
+                    min = V2::new(min.x.min(v.x), min.y.min(v.y));
+                    max = V2::new(max.x.max(v.x), max.y.max(v.y));
+                }
+                Rectangle::from_coords(min, max)
+            },
+        }
+    }
 }
 
 pub struct Circle {
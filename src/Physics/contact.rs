@@ -0,0 +1,50 @@
+use crate::V2;
+use crate::entity::EntityHandle;
+use super::{Manifold, RigidBodyHandle};
+
+/// Where two bodies touch, passed to the collision callbacks of `EntityBehavior`
+#[derive(Clone, Copy)]
+pub struct Contact {
+    /// Points from the entity receiving the callback towards the other entity
+    pub normal: V2,
+    pub penetration: f32,
+    points: [V2; 2],
+    point_count: usize,
+}
+impl Contact {
+    pub(super) fn from_manifold(manifold: &Manifold) -> Contact {
+        Contact {
+            normal: manifold.normal,
+            penetration: manifold.penetration,
+            points: manifold.contacts,
+            point_count: manifold.contact_count,
+        }
+    }
+
+    /// The same contact seen from the other body
+    pub(super) fn flip(&self) -> Contact {
+        Contact { normal: -self.normal, ..*self }
+    }
+
+    pub fn points(&self) -> &[V2] {
+        &self.points[..self.point_count]
+    }
+}
+
+/// A pair of bodies that were touching at the end of the last step
+pub(super) struct Touching {
+    /// Owners of the bodies in the same order as the pair's key
+    pub owners: (EntityHandle, EntityHandle),
+    pub sensor: bool,
+}
+impl Touching {
+    pub fn new(key: (RigidBodyHandle, RigidBodyHandle), a: RigidBodyHandle, owner_a: EntityHandle, owner_b: EntityHandle, sensor: bool) -> Touching {
+        let owners = if key.0 == a { (owner_a, owner_b) } else { (owner_b, owner_a) };
+        Touching { owners, sensor }
+    }
+}
+
+/// Pairs are stored in the same order no matter which order the broadphase returned them in
+pub(super) fn pair_key(a: RigidBodyHandle, b: RigidBodyHandle) -> (RigidBodyHandle, RigidBodyHandle) {
+    if a.index < b.index { (a, b) } else { (b, a) }
+}
@@ -0,0 +1,170 @@
+use crate::V2;
+use crate::generational_array::GenerationalIndex;
+use super::{RigidBody, RigidBodyHandle, cross, cross_v2};
+use cgmath::InnerSpace;
+
+/// Fraction of the position error corrected each step
+const BAUMGARTE: f32 = 0.2;
+
+pub type JointHandle = GenerationalIndex;
+
+pub enum JointKind {
+    /// Keeps the anchors exactly `length` apart
+    Distance(f32),
+    /// Keeps the anchors at most `length` apart
+    Rope(f32),
+    /// Pins the anchors together, the bodies can still rotate
+    Revolute,
+    /// Pins the anchors together and keeps the rotation between the bodies
+    Weld(f32),
+}
+
+/// Constraint between two rigid bodies, joints are destroyed with either body
+pub struct Joint {
+    pub(super) body_a: RigidBodyHandle,
+    pub(super) body_b: RigidBodyHandle,
+    /// Offsets from each entity's position before it is rotated
+    anchor_a: V2,
+    anchor_b: V2,
+    kind: JointKind,
+}
+impl Joint {
+    /// Keeps the anchors, given relative to each entity, `length` apart
+    pub fn distance(body_a: RigidBodyHandle, body_b: RigidBodyHandle, anchor_a: V2, anchor_b: V2, length: f32) -> JointHandle {
+        Self::attach(body_a, body_b, anchor_a, anchor_b, JointKind::Distance(length))
+    }
+
+    /// Stops the anchors, given relative to each entity, from moving more than `length` apart
+    pub fn rope(body_a: RigidBodyHandle, body_b: RigidBodyHandle, anchor_a: V2, anchor_b: V2, length: f32) -> JointHandle {
+        Self::attach(body_a, body_b, anchor_a, anchor_b, JointKind::Rope(length))
+    }
+
+    /// Pins both bodies to `pivot`, given in world space
+    pub fn revolute(body_a: RigidBodyHandle, body_b: RigidBodyHandle, pivot: V2) -> JointHandle {
+        let (anchor_a, _) = unsafe { local_anchor(body_a, pivot) };
+        let (anchor_b, _) = unsafe { local_anchor(body_b, pivot) };
+        Self::attach(body_a, body_b, anchor_a, anchor_b, JointKind::Revolute)
+    }
+
+    /// Fixes both bodies together at `pivot`, given in world space
+    pub fn weld(body_a: RigidBodyHandle, body_b: RigidBodyHandle, pivot: V2) -> JointHandle {
+        let (anchor_a, rotation_a) = unsafe { local_anchor(body_a, pivot) };
+        let (anchor_b, rotation_b) = unsafe { local_anchor(body_b, pivot) };
+        Self::attach(body_a, body_b, anchor_a, anchor_b, JointKind::Weld(rotation_b - rotation_a))
+    }
+
+    fn attach(body_a: RigidBodyHandle, body_b: RigidBodyHandle, anchor_a: V2, anchor_b: V2, kind: JointKind) -> JointHandle {
+        let physics = super::physics();
+        physics.joints.push(Joint { body_a, body_b, anchor_a, anchor_b, kind }).0
+    }
+
+    pub fn destroy(handle: JointHandle) {
+        let physics = super::physics();
+        physics.joints.remove(&handle);
+    }
+
+    /// Applies the impulse needed to satisfy the joint, called once per solver iteration
+    pub(super) unsafe fn apply_impulse(&self, body_a: &mut RigidBody, body_b: &mut RigidBody, delta_time: f32) {
+        let entity_a = &*body_a.entity;
+        let entity_b = &*body_b.entity;
+
+        let ra = rotate(self.anchor_a, entity_a.rotation);
+        let rb = rotate(self.anchor_b, entity_b.rotation);
+        let mut anchors = Anchors {
+            ra,
+            rb,
+            error: (entity_b.position + rb) - (entity_a.position + ra),
+            rv: relative_velocity(body_a, body_b, ra, rb),
+        };
+
+        match self.kind {
+            JointKind::Distance(length) => apply_axis_impulse(body_a, body_b, &anchors, length, false, delta_time),
+            JointKind::Rope(length) => apply_axis_impulse(body_a, body_b, &anchors, length, true, delta_time),
+            JointKind::Revolute => apply_point_impulse(body_a, body_b, &anchors, delta_time),
+            JointKind::Weld(rotation) => {
+                let inverse_inertia = body_a.inverse_inertia + body_b.inverse_inertia;
+                if inverse_inertia > 0. {
+                    let angle_error = (entity_b.rotation - entity_a.rotation) - rotation;
+                    let angular_velocity = body_b.angular_velocity - body_a.angular_velocity;
+                    let j = -(angular_velocity + angle_error * BAUMGARTE / delta_time) / inverse_inertia;
+                    body_a.angular_velocity -= j * body_a.inverse_inertia;
+                    body_b.angular_velocity += j * body_b.inverse_inertia;
+                }
+
+                // Relative velocity changed with the angular impulse
+                anchors.rv = relative_velocity(body_a, body_b, ra, rb);
+                apply_point_impulse(body_a, body_b, &anchors, delta_time)
+            },
+        }
+    }
+}
+
+/// World space offsets of the anchors and how far the joint is from being satisfied
+#[derive(Clone, Copy)]
+struct Anchors {
+    ra: V2,
+    rb: V2,
+    /// Offset from anchor a to anchor b
+    error: V2,
+    /// Velocity of anchor b relative to anchor a
+    rv: V2,
+}
+
+fn relative_velocity(body_a: &RigidBody, body_b: &RigidBody, ra: V2, rb: V2) -> V2 {
+    body_b.velocity + cross(body_b.angular_velocity, rb) - body_a.velocity - cross(body_a.angular_velocity, ra)
+}
+
+/// Keeps the anchors `length` apart along the line between them, ropes only pull
+fn apply_axis_impulse(body_a: &mut RigidBody, body_b: &mut RigidBody, anchors: &Anchors, length: f32, slack: bool, delta_time: f32) {
+    let Anchors { ra, rb, error, rv } = *anchors;
+    let distance = error.magnitude();
+    if distance < f32::EPSILON { return; }
+
+    let stretch = distance - length;
+    if slack && stretch <= 0. { return; }
+
+    let n = error / distance;
+    let ra_cross_n = cross_v2(ra, n);
+    let rb_cross_n = cross_v2(rb, n);
+    let inv_mass_sum = body_a.inverse_mass + body_b.inverse_mass +
+                       ra_cross_n * ra_cross_n * body_a.inverse_inertia + rb_cross_n * rb_cross_n * body_b.inverse_inertia;
+    if inv_mass_sum == 0. { return; }
+
+    let j = -(rv.dot(n) + stretch * BAUMGARTE / delta_time) / inv_mass_sum;
+    // A rope can't push the bodies apart
+    if slack && j > 0. { return; }
+
+    let impulse = n * j;
+    body_a.apply_impulse(impulse * -1., ra);
+    body_b.apply_impulse(impulse, rb);
+}
+
+/// Keeps the anchors at the same point
+fn apply_point_impulse(body_a: &mut RigidBody, body_b: &mut RigidBody, anchors: &Anchors, delta_time: f32) {
+    let Anchors { ra, rb, error, rv } = *anchors;
+    // Effective mass of a point constraint, see Box2D's revolute joint
+    let (ma, mb) = (body_a.inverse_mass, body_b.inverse_mass);
+    let (ia, ib) = (body_a.inverse_inertia, body_b.inverse_inertia);
+    let k11 = ma + mb + ia * ra.y * ra.y + ib * rb.y * rb.y;
+    let k12 = -ia * ra.x * ra.y - ib * rb.x * rb.y;
+    let k22 = ma + mb + ia * ra.x * ra.x + ib * rb.x * rb.x;
+
+    let determinant = k11 * k22 - k12 * k12;
+    if determinant.abs() < f32::EPSILON { return; }
+
+    let c = -(rv + error * (BAUMGARTE / delta_time));
+    let impulse = V2::new((k22 * c.x - k12 * c.y) / determinant, (k11 * c.y - k12 * c.x) / determinant);
+    body_a.apply_impulse(impulse * -1., ra);
+    body_b.apply_impulse(impulse, rb);
+}
+
+/// Offset of `point` from the body's entity with the entity's rotation removed, and the entity's rotation
+unsafe fn local_anchor(body: RigidBodyHandle, point: V2) -> (V2, f32) {
+    let entity = &*RigidBody::get(body).entity;
+    (rotate(point - entity.position, -entity.rotation), entity.rotation)
+}
+
+fn rotate(v: V2, radians: f32) -> V2 {
+    let (s, c) = radians.sin_cos();
+    V2::new(v.x * c - v.y * s, v.x * s + v.y * c)
+}
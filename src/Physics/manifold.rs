@@ -1,10 +1,10 @@
 use crate::V2;
-use crate::physics::{RigidBody, cross};
+use crate::physics::{RigidBody, RigidBodyHandle, cross};
 use cgmath::InnerSpace;
 
 pub struct ManifoldHandle {
-    pub body_a: usize,
-    pub body_b: usize,
+    pub body_a: RigidBodyHandle,
+    pub body_b: RigidBodyHandle,
     pub manifold: Manifold,
 }
 
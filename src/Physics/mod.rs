@@ -7,21 +7,35 @@ mod collision_shape;
 mod manifold;
 mod collision;
 mod quad_tree;
+mod broadphase;
+mod query;
+mod contact;
+mod joint;
 pub use quad_tree::QuadTree;
 pub use collision_shape::{CollisionShape, Circle, Polygon};
-pub use rigid_body::{PhysicsMaterial, RigidBody, RigidBodyHandle};
+pub use rigid_body::{PhysicsMaterial, RigidBody, RigidBodyHandle, BodyType};
+pub use contact::Contact;
+#[allow(unused_imports)]
+pub use joint::{Joint, JointHandle, JointKind};
+#[allow(unused_imports)]
+pub use query::{RaycastHit, raycast, shape_cast};
 use manifold::{Manifold, ManifoldHandle};
+use broadphase::Broadphase;
+use contact::{Touching, pair_key};
+use crate::entity::EntityHandle;
+use std::collections::HashMap;
 use crate::generational_array::GenerationalArray;
-use crate::entity::MAX_ENTITIES;
+use crate::entity::ENTITY_CHUNK_SIZE;
 
-crate::singleton!(physics: Physics = Physics { bodies: GenerationalArray::new(), gravity: V2::new(0., 0.) });
+crate::singleton!(physics: Physics = Physics { bodies: GenerationalArray::new(), joints: GenerationalArray::new(), broadphase: Broadphase::new(), touching: HashMap::new(), gravity: V2::new(0., 0.) });
 pub const PHYSICS_ITERATIONS: u8 = 10;
 
 #[macro_export]
 macro_rules! physics_layer_enum {
-    ($vis:vis enum $name:ident {
+    ($(#[$meta:meta])* $vis:vis enum $name:ident {
         $($key:ident = $value:expr,)+
     }) => {
+        $(#[$meta])*
         #[repr(u8)]
         $vis enum $name {
             $($key = $value,)+
@@ -31,11 +45,18 @@ macro_rules! physics_layer_enum {
                 self as u8
             }
         }
+        impl $name {
+            /// Name and value of every layer, see `PrefabLibrary::register_layers`
+            pub const LAYERS: &'static [(&'static str, u8)] = &[$((stringify!($key), $name::$key as u8),)+];
+        }
     }
 }
 
 pub struct Physics {
-    bodies: GenerationalArray<RigidBody, MAX_ENTITIES>,
+    bodies: GenerationalArray<RigidBody, ENTITY_CHUNK_SIZE>,
+    joints: GenerationalArray<Joint, 64>,
+    broadphase: Broadphase,
+    touching: HashMap<(RigidBodyHandle, RigidBodyHandle), Touching>,
     gravity: V2,
 }
 
@@ -49,22 +70,27 @@ fn cross(a: f32, b: V2) -> V2 {
 
 
 unsafe fn solve_manifold(body_a: &RigidBody, body_b: &RigidBody) -> Manifold {
-    let mut m = Manifold::new();
-    let entity_a = &*body_a.entity;
-    let entity_b = &*body_b.entity;
+    collide(&body_a.shape, (*body_a.entity).position, &body_b.shape, (*body_b.entity).position)
+}
 
-    match (&body_a.shape, &body_b.shape) {
-        (CollisionShape::Circle(a), CollisionShape::Circle(b)) => collision::circle_to_circle(&mut m, entity_a, entity_b, a, b),
-        (CollisionShape::Polygon(b), CollisionShape::Circle(a)) => collision::circle_to_polygon(&mut m, entity_a, entity_b, a, b),
-        (CollisionShape::Circle(a), CollisionShape::Polygon(b)) => collision::circle_to_polygon(&mut m, entity_b, entity_a, a, b),
-        (CollisionShape::Polygon(a), CollisionShape::Polygon(b)) => collision::polygon_to_polygon(&mut m, entity_a, entity_b, a, b),
+fn collide(shape_a: &CollisionShape, position_a: V2, shape_b: &CollisionShape, position_b: V2) -> Manifold {
+    let mut m = Manifold::new();
+    match (shape_a, shape_b) {
+        (CollisionShape::Circle(a), CollisionShape::Circle(b)) => collision::circle_to_circle(&mut m, position_a, position_b, a, b),
+        (CollisionShape::Polygon(a), CollisionShape::Circle(b)) => {
+            // Normal points from the circle to the polygon, flip it so it points from a to b
+            collision::circle_to_polygon(&mut m, position_b, position_a, b, a);
+            m.normal = -m.normal;
+        },
+        (CollisionShape::Circle(a), CollisionShape::Polygon(b)) => collision::circle_to_polygon(&mut m, position_a, position_b, a, b),
+        (CollisionShape::Polygon(a), CollisionShape::Polygon(b)) => collision::polygon_to_polygon(&mut m, position_a, position_b, a, b),
     }
     m
 }
 
 // see http://www.niksula.hut.fi/~hkankaan/Homepages/gravity.html
 fn integrate_forces(body: &mut RigidBody, gravity: V2, delta_time: f32) {
-	if body.inverse_mass == 0. {
+	if body.inverse_mass == 0. || unsafe { body.is_paused() } {
 		return;
     }
 
@@ -73,7 +99,7 @@ fn integrate_forces(body: &mut RigidBody, gravity: V2, delta_time: f32) {
 }
 
 unsafe fn integrate_velocity(body: &mut RigidBody, gravity: V2, delta_time: f32) {
-	if body.inverse_mass == 0. {
+	if body.inverse_mass == 0. || body.is_paused() {
 		return;
     }
 
@@ -84,42 +110,51 @@ unsafe fn integrate_velocity(body: &mut RigidBody, gravity: V2, delta_time: f32)
 	integrate_forces(body, gravity, delta_time);
 }
 
+unsafe fn notify_exit(body: Option<&mut RigidBody>, other: EntityHandle, sensor: bool, messages: &mut crate::messages::MessageBus) {
+    if let Some(body) = body {
+        let entity = &mut *body.entity;
+        if sensor { entity.notify_overlap_exit(other, messages); }
+        else { entity.notify_collision_exit(other, messages); }
+    }
+}
+
 pub unsafe fn step_physics(delta_time: f32, messages: &mut crate::messages::MessageBus) {
     let physics = physics();
     let bodies = &mut physics.bodies;
 
+    // Kinematic bodies are moved by code, their velocity is only used to resolve collisions with them
+    for b in bodies.iter_index() {
+        let b = bodies.get_mut(&b).unwrap();
+        if b.body_type == BodyType::Kinematic && delta_time > 0. {
+            b.velocity = ((*b.entity).position - b.last_position) / delta_time;
+        }
+    }
+
+    query::sweep_continuous(bodies, &mut physics.broadphase);
+    for b in bodies.iter_index() {
+        let b = bodies.get_mut(&b).unwrap();
+        b.last_position = (*b.entity).position;
+    }
+
 	// Generate new collision info
 	let mut contacts = vec!();
-    let mut i = 0;
-    while i < bodies.len() {
-        if let Some(a) = bodies.get_at(i) {
-
-            let mut j = i + 1;
-            while j < bodies.len() {
-                if let Some(b) = bodies.get_at(j) {
-
-                    if a.inverse_mass + b.inverse_mass == 0. || 
-                       a.colliding_layers & b.layer == 0 {
-                        // both objects are static, no collision will occur
-                        // objects will not collide due to layers
-                    } else {
-                        // objects will collide
-                        let manifold = solve_manifold(a, b);
-                        if manifold.contact_count > 0 {
-                            let handle = ManifoldHandle {
-                                body_a: i,
-                                body_b: j,
-                                manifold,
-                            };
-                            contacts.push(handle);
-                        }
-                    }
-                }
-
-                j += 1;
-            }
+    let mut overlaps = vec!();
+    for (i, j) in physics.broadphase.pairs(bodies) {
+        let a = bodies.get(&i).unwrap();
+        let b = bodies.get(&j).unwrap();
+
+        let manifold = solve_manifold(a, b);
+        if manifold.contact_count > 0 && (a.sensor || b.sensor) {
+            // Sensors take no part in resolution
+            overlaps.push((i, j));
+        } else if manifold.contact_count > 0 {
+            let handle = ManifoldHandle {
+                body_a: i,
+                body_b: j,
+                manifold,
+            };
+            contacts.push(handle);
         }
-        i += 1;
     }
 
 	// Integrate forces
@@ -130,18 +165,26 @@ pub unsafe fn step_physics(delta_time: f32, messages: &mut crate::messages::Mess
 
 	// Initialize collision
 	for c in &mut contacts {
-        let a = &bodies.get_at(c.body_a).unwrap();
-        let b = &bodies.get_at(c.body_b).unwrap();
+        let a = &bodies.get(&c.body_a).unwrap();
+        let b = &bodies.get(&c.body_b).unwrap();
 		c.manifold.initialize(delta_time, physics.gravity, a, b);
     }
 
-	// Solve collisions
+	// Solve collisions and joints
 	for _ in 0..PHYSICS_ITERATIONS {
 		for c in &contacts {
-            let a = &mut *(bodies.get_at_mut(c.body_a).unwrap() as *mut _);
-            let b = &mut *(bodies.get_at_mut(c.body_b).unwrap() as *mut _);
+            let a = &mut *(bodies.get_mut(&c.body_a).unwrap() as *mut _);
+            let b = &mut *(bodies.get_mut(&c.body_b).unwrap() as *mut _);
             c.manifold.apply_impulse(a, b);
         }
+        for j in physics.joints.iter() {
+            // Joints correct their error over time which needs time to pass
+            if j.body_a == j.body_b || delta_time <= 0. { continue; }
+            let a: &mut RigidBody = match bodies.get_mut(&j.body_a) { Some(a) => &mut *(a as *mut _), None => continue };
+            let b: &mut RigidBody = match bodies.get_mut(&j.body_b) { Some(b) => &mut *(b as *mut _), None => continue };
+            if a.is_paused() || b.is_paused() { continue; }
+            j.apply_impulse(a, b, delta_time);
+        }
     }
 
 	// Integrate velocities
@@ -152,17 +195,53 @@ pub unsafe fn step_physics(delta_time: f32, messages: &mut crate::messages::Mess
 
 	// Correct positions
     for c in &contacts {
-        let a = &mut *(bodies.get_at_mut(c.body_a).unwrap() as *mut _);
-        let b = &mut *(bodies.get_at_mut(c.body_b).unwrap() as *mut _);
+        let a = &mut *(bodies.get_mut(&c.body_a).unwrap() as *mut _);
+        let b = &mut *(bodies.get_mut(&c.body_b).unwrap() as *mut _);
         c.manifold.positional_correction(a, b);
     }
 
-    //Notify entities of collision
+    // Notify entities of contacts that started or continued this step
+    let mut previous = std::mem::take(&mut physics.touching);
     for c in &contacts {
-        let a: &mut RigidBody = &mut *(bodies.get_at_mut(c.body_a).unwrap() as *mut _);
-        let b: &mut RigidBody = &mut *(bodies.get_at_mut(c.body_b).unwrap() as *mut _);
-        a.notify_collision(b, messages);
-        b.notify_collision(a, messages);
+        let a: &mut RigidBody = &mut *(bodies.get_mut(&c.body_a).unwrap() as *mut _);
+        let b: &mut RigidBody = &mut *(bodies.get_mut(&c.body_b).unwrap() as *mut _);
+        let key = pair_key(c.body_a, c.body_b);
+        let contact = Contact::from_manifold(&c.manifold);
+
+        if previous.remove(&key).is_some() {
+            a.notify_collision_stay(b, &contact, messages);
+            b.notify_collision_stay(a, &contact.flip(), messages);
+        } else {
+            a.notify_collision_enter(b, &contact, messages);
+            b.notify_collision_enter(a, &contact.flip(), messages);
+        }
+        physics.touching.insert(key, Touching::new(key, c.body_a, a.owner, b.owner, false));
+    }
+    for (i, j) in overlaps {
+        let a: &mut RigidBody = &mut *(bodies.get_mut(&i).unwrap() as *mut _);
+        let b: &mut RigidBody = &mut *(bodies.get_mut(&j).unwrap() as *mut _);
+        let key = pair_key(i, j);
+
+        if previous.remove(&key).is_none() {
+            a.notify_overlap(b, messages);
+            b.notify_overlap(a, messages);
+        }
+        physics.touching.insert(key, Touching::new(key, i, a.owner, b.owner, true));
+    }
+
+    // Anything left stopped touching, the other body may have been destroyed since the last step
+    for ((i, j), t) in previous {
+        // Bodies in a scene that isn't updating are skipped by the broadphase, they are still touching when it resumes
+        let paused_i = bodies.get(&i).is_some_and(|b| b.is_paused());
+        let paused_j = bodies.get(&j).is_some_and(|b| b.is_paused());
+        if paused_i || paused_j {
+            physics.touching.insert((i, j), t);
+            continue;
+        }
+
+        let (owner_i, owner_j) = t.owners;
+        notify_exit(bodies.get_mut(&i), owner_j, t.sensor, messages);
+        notify_exit(bodies.get_mut(&j), owner_i, t.sensor, messages);
     }
 
 	// Clear all forces
@@ -2,6 +2,7 @@ use crate::V2;
 use crate::entity::{EntityHandle, EntityManager};
 use crate::utils::{Rectangle, init_optional_array_to_blank};
 use cgmath::MetricSpace;
+use std::collections::HashSet;
 
 const MAX_LEVELS: usize = 8;
 
@@ -24,12 +25,13 @@ impl QuadTree {
     // TODO this should be split to return handles or the behaviors
     pub fn within_distance<T: 'static>(&self, position: V2, distance: f32, entities: &EntityManager) -> Vec<EntityHandle> {
         let rect = Rectangle::new(V2::new(position.x - distance, position.y - distance), V2::new(distance * 2., distance * 2.));
-        let close = self.find(rect);
+        let close = self.find(rect).iter().collect::<HashSet<_>>();
+        // Only entities of the type index that are near enough are checked
         let mut results = vec!();
-        for e in close {
+        for e in entities.handles_of::<T>() {
+            if !close.contains(e) { continue; }
             let entity = entities.get(e).unwrap();
-            if entity.position.distance2(position) < distance * distance &&
-               entity.as_any().downcast_ref::<T>().is_some() {
+            if entity.position.distance2(position) < distance * distance {
                 results.push(*e);
             }
         }
@@ -0,0 +1,202 @@
+use crate::V2;
+use crate::entity::EntityHandle;
+use crate::utils::Rectangle;
+use crate::generational_array::GenerationalArray;
+use crate::entity::ENTITY_CHUNK_SIZE;
+use super::{RigidBody, RigidBodyHandle, CollisionShape, Circle, Polygon, collide};
+use super::broadphase::{Broadphase, can_collide};
+use cgmath::{InnerSpace, Matrix};
+
+/// Number of halvings used to find the first point of contact of a shape cast
+const SHAPE_CAST_ITERATIONS: u8 = 12;
+
+pub struct RaycastHit {
+    pub entity: EntityHandle,
+    pub body: RigidBodyHandle,
+    pub point: V2,
+    /// Surface normal of the body that was hit, facing the ray
+    pub normal: V2,
+    pub distance: f32,
+}
+
+/// Returns the first body along the ray that is on one of the layers in `layer_mask`
+/// Sensors and bodies in paused scenes are ignored, a ray with no direction hits nothing
+pub fn raycast(origin: V2, direction: V2, max_distance: f32, layer_mask: u8) -> Option<RaycastHit> {
+    if direction.magnitude2() == 0. { return None; }
+    let direction = direction.normalize();
+    let end = origin + direction * max_distance;
+    let bounds = Rectangle::from_coords(V2::new(origin.x.min(end.x), origin.y.min(end.y)),
+                                        V2::new(origin.x.max(end.x), origin.y.max(end.y)));
+
+    let physics = super::physics();
+    closest(&physics.bodies, &mut physics.broadphase, bounds, layer_filter(layer_mask), |body, position| {
+        let (distance, normal) = match &body.shape {
+            CollisionShape::Circle(c) => ray_to_circle(origin, direction, position, c)?,
+            CollisionShape::Polygon(p) => ray_to_polygon(origin, direction, position, p)?,
+        };
+        if distance > max_distance { return None; }
+        Some((distance, origin + direction * distance, normal))
+    })
+}
+
+/// Moves `shape` from `origin` along `direction` and returns the first body it touches that is on one of the layers in `layer_mask`
+/// Sensors and bodies in paused scenes are ignored, a cast with no direction hits nothing
+pub fn shape_cast(shape: &CollisionShape, origin: V2, direction: V2, max_distance: f32, layer_mask: u8) -> Option<RaycastHit> {
+    if direction.magnitude2() == 0. { return None; }
+    let physics = super::physics();
+    let cast = Cast { origin, direction: direction.normalize(), max_distance };
+    sweep(&physics.bodies, &mut physics.broadphase, shape, cast, false, layer_filter(layer_mask))
+}
+
+/// Moves continuous bodies back to the first body they hit since the last step so fast bodies can't pass through anything
+pub(super) unsafe fn sweep_continuous(bodies: &GenerationalArray<RigidBody, ENTITY_CHUNK_SIZE>, broadphase: &mut Broadphase) {
+    for handle in bodies.iter_index() {
+        let body = bodies.get(&handle).unwrap();
+        if !body.continuous || body.is_paused() { continue; }
+
+        let entity = &mut *body.entity;
+        let travel = entity.position - body.last_position;
+        let distance = travel.magnitude();
+        if distance > 0. {
+            let filter = |other: &RigidBody| other.owner != body.owner && can_collide(body, other);
+            // Bodies that were already touching at the start are left to the narrowphase
+            let cast = Cast { origin: body.last_position, direction: travel / distance, max_distance: distance };
+            if let Some(hit) = sweep(bodies, broadphase, &body.shape, cast, true, filter) {
+                entity.position = body.last_position + travel / distance * hit.distance;
+            }
+        }
+    }
+}
+
+fn layer_filter(layer_mask: u8) -> impl Fn(&RigidBody) -> bool {
+    // Bodies are destroyed before their entity is disposed so the entity is always valid
+    move |body| !body.sensor && body.layer & layer_mask != 0 && !unsafe { body.is_paused() }
+}
+
+/// Where a shape starts and how far it moves in a sweep
+struct Cast {
+    origin: V2,
+    direction: V2,
+    max_distance: f32,
+}
+
+fn sweep(bodies: &GenerationalArray<RigidBody, ENTITY_CHUNK_SIZE>, broadphase: &mut Broadphase, shape: &CollisionShape, cast: Cast,
+         skip_touching: bool, filter: impl Fn(&RigidBody) -> bool) -> Option<RaycastHit> {
+    let Cast { origin, direction, max_distance } = cast;
+    let start = shape.bounds(origin);
+    let end = shape.bounds(origin + direction * max_distance);
+    let bounds = Rectangle::from_coords(V2::new(start.left().min(end.left()), start.top().min(end.top())),
+                                        V2::new(start.right().max(end.right()), start.bottom().max(end.bottom())));
+
+    // Stepping by less than the size of the shape means nothing it passes over is skipped
+    let step = f32::max(f32::min(start.width(), start.height()) / 2., 0.5);
+
+    closest(bodies, broadphase, bounds, filter, |body, position| {
+        let touches = |distance: f32| collide(shape, origin + direction * distance, &body.shape, position);
+
+        let mut free = 0.;
+        let mut distance = 0.;
+        while touches(distance).contact_count == 0 {
+            if distance >= max_distance { return None; }
+            free = distance;
+            distance = f32::min(distance + step, max_distance);
+        }
+        if distance == 0. && skip_touching { return None; }
+
+        if distance > 0. {
+            for _ in 0..SHAPE_CAST_ITERATIONS {
+                let middle = (free + distance) / 2.;
+                if touches(middle).contact_count == 0 { free = middle; } else { distance = middle; }
+            }
+        }
+
+        // Manifold normals point from the cast shape to the body
+        let m = touches(distance);
+        Some((distance, m.contacts[0], -m.normal))
+    })
+}
+
+/// Finds the closest hit returned by `test` for bodies that pass `filter` and whose bounds overlap `bounds`
+fn closest(bodies: &GenerationalArray<RigidBody, ENTITY_CHUNK_SIZE>, broadphase: &mut Broadphase, bounds: Rectangle,
+           filter: impl Fn(&RigidBody) -> bool, test: impl Fn(&RigidBody, V2) -> Option<(f32, V2, V2)>) -> Option<RaycastHit> {
+    let mut result: Option<RaycastHit> = None;
+    for handle in unsafe { broadphase.query(bodies, &bounds) } {
+        let body = match bodies.get(&handle) {
+            Some(b) => b,
+            None => continue,
+        };
+        if !filter(body) { continue; }
+
+        let position = unsafe { (*body.entity).position };
+        if let Some((distance, point, normal)) = test(body, position) &&
+           result.as_ref().is_none_or(|r| distance < r.distance) {
+            result = Some(RaycastHit { entity: body.owner, body: handle, point, normal, distance });
+        }
+    }
+    result
+}
+
+fn ray_to_circle(origin: V2, direction: V2, center: V2, circle: &Circle) -> Option<(f32, V2)> {
+    let m = origin - center;
+    let b = m.dot(direction);
+    let c = m.magnitude2() - circle.radius() * circle.radius();
+
+    // Starts outside and points away
+    if c > 0. && b > 0. { return None; }
+    let discriminant = b * b - c;
+    if discriminant < 0. { return None; }
+
+    // Rays starting inside hit at their origin
+    let distance = f32::max(-b - discriminant.sqrt(), 0.);
+    let normal = origin + direction * distance - center;
+    let normal = if normal.magnitude2() == 0. { -direction } else { normal.normalize() };
+    Some((distance, normal))
+}
+
+fn ray_to_polygon(origin: V2, direction: V2, position: V2, polygon: &Polygon) -> Option<(f32, V2)> {
+    // Clip the ray against each face in the polygon's model space
+    let u_t = polygon.u.transpose();
+    let o = u_t * (origin - position);
+    let d = u_t * direction;
+
+    let mut enter = 0.;
+    let mut exit = f32::MAX;
+    let mut face = None;
+    for (v, n) in polygon.vertices().iter().zip(polygon.normals()) {
+        let numerator = n.dot(*v - o);
+        let denominator = n.dot(d);
+
+        if denominator == 0. {
+            // Parallel to and outside of this face
+            if numerator < 0. { return None; }
+        } else if denominator < 0. {
+            let t = numerator / denominator;
+            if t > enter { enter = t; face = Some(*n); }
+        } else {
+            exit = f32::min(exit, numerator / denominator);
+        }
+
+        if enter > exit { return None; }
+    }
+
+    let normal = match face {
+        Some(n) => polygon.u * n,
+        // The ray starts inside the polygon
+        None => -direction,
+    };
+    Some((enter, normal))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_direction_hits_nothing() {
+        let origin = V2::new(10., 10.);
+        assert!(raycast(origin, V2::new(0., 0.), 100., u8::MAX).is_none());
+
+        let shape = CollisionShape::Circle(Circle::new(5.));
+        assert!(shape_cast(&shape, origin, V2::new(0., 0.), 100., u8::MAX).is_none());
+    }
+}
@@ -1,6 +1,7 @@
 use crate::V2;
-use crate::entity::Entity;
+use crate::entity::{Entity, EntityHandle};
 
+#[derive(serde::Deserialize, Clone, Copy)]
 pub struct PhysicsMaterial {
     pub(super) static_friction: f32,
     pub(super) dynamic_friction: f32,
@@ -17,9 +18,23 @@ impl PhysicsMaterial {
     }
 }
 
+/// How a body takes part in collision resolution
+#[derive(serde::Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum BodyType {
+    /// Moved by forces and pushed by other bodies
+    #[default]
+    Dynamic,
+    /// Moved by code, pushes dynamic bodies but is never pushed back
+    Kinematic,
+    /// Never moves
+    Static,
+}
+
 pub type RigidBodyHandle = crate::generational_array::GenerationalIndex;
 pub struct RigidBody {
     pub(super) entity: *mut Entity,
+    pub(super) owner: EntityHandle,
+    pub(super) body_type: BodyType,
     
     pub(super) layer: u8,
     pub(super) colliding_layers: u8,
@@ -38,9 +53,25 @@ pub struct RigidBody {
     pub(super) dynamic_friction: f32,
     pub(super) restitution: f32,
     pub(super) shape: super::CollisionShape,
+    /// Sensors report overlaps but are never pushed by or push other bodies
+    pub(super) sensor: bool,
+    /// Continuous bodies are swept from where they were at the last step so they can't pass through other bodies
+    pub(super) continuous: bool,
+    pub(super) last_position: V2,
 }
 impl RigidBody {
-    pub fn attach(entity: *mut Entity, material: PhysicsMaterial, shape: super::CollisionShape, layer: u8, colliding_layers: u8) -> RigidBodyHandle {
+    pub fn attach(entity: *mut Entity, owner: EntityHandle, material: PhysicsMaterial, shape: super::CollisionShape, body_type: BodyType, layer: u8, colliding_layers: u8) -> RigidBodyHandle {
+        Self::push(entity, owner, Some(material), shape, body_type, layer, colliding_layers)
+    }
+
+    /// Attaches a body that only detects overlaps, it has no mass and is moved with its entity
+    pub fn attach_sensor(entity: *mut Entity, owner: EntityHandle, shape: super::CollisionShape, layer: u8, colliding_layers: u8) -> RigidBodyHandle {
+        Self::push(entity, owner, None, shape, BodyType::Kinematic, layer, colliding_layers)
+    }
+
+    fn push(entity: *mut Entity, owner: EntityHandle, material: Option<PhysicsMaterial>, shape: super::CollisionShape, body_type: BodyType, layer: u8, colliding_layers: u8) -> RigidBodyHandle {
+        let sensor = material.is_none();
+        let material = material.unwrap_or(PhysicsMaterial::new(0., 0., 0., 0.));
         let (mass, inertia) = match &shape {
             super::CollisionShape::Circle(c) => {
                 let m = std::f64::consts::PI as f32 * c.radius() * c.radius() * material.density;
@@ -52,8 +83,13 @@ impl RigidBody {
             }
         };
 
+        // Only dynamic bodies are moved by the solver
+        let (mass, inertia) = if body_type == BodyType::Dynamic { (mass, inertia) } else { (0., 0.) };
+
         let body = RigidBody {
             entity,
+            owner,
+            body_type,
             layer,
             colliding_layers,
             velocity: V2::new(0., 0.),
@@ -68,9 +104,14 @@ impl RigidBody {
             dynamic_friction: material.dynamic_friction,
             restitution: material.restitution,
             shape,
+            sensor,
+            continuous: false,
+            last_position: unsafe { (*entity).position },
         };
         let physics = super::physics();
-        physics.bodies.push(body).0
+        let handle = physics.bodies.push(body).0;
+        physics.broadphase.insert(handle);
+        handle
     }
 
     pub fn apply_impulse(&mut self, impulse: V2, contact: V2) {
@@ -86,14 +127,56 @@ impl RigidBody {
         self.force += force;
     }
 
-    pub unsafe fn notify_collision(&mut self, other: &RigidBody, messages: &mut crate::messages::MessageBus) {
+    pub unsafe fn notify_collision_enter(&mut self, other: &RigidBody, contact: &super::Contact, messages: &mut crate::messages::MessageBus) {
         let entity = &mut *self.entity;
         let other = &*other.entity;
-        entity.notify_collision(other, messages)
+        entity.notify_collision_enter(other, contact, messages)
     }
 
+    pub unsafe fn notify_collision_stay(&mut self, other: &RigidBody, contact: &super::Contact, messages: &mut crate::messages::MessageBus) {
+        let entity = &mut *self.entity;
+        let other = &*other.entity;
+        entity.notify_collision_stay(other, contact, messages)
+    }
+
+    pub unsafe fn notify_overlap(&mut self, other: &RigidBody, messages: &mut crate::messages::MessageBus) {
+        let entity = &mut *self.entity;
+        let other = &*other.entity;
+        entity.notify_overlap(other, messages)
+    }
+
+    /// Entity the body is attached to
+    pub fn owner(&self) -> EntityHandle {
+        self.owner
+    }
+
+    /// Moving a continuous body with `set_position` sweeps it through everything in between
+    pub fn set_continuous(&mut self, continuous: bool) {
+        self.continuous = continuous;
+    }
+
+    pub fn body_type(&self) -> BodyType {
+        self.body_type
+    }
+
+    pub fn is_sensor(&self) -> bool {
+        self.sensor
+    }
+
+    pub(super) unsafe fn is_paused(&self) -> bool {
+        (*self.entity).paused()
+    }
+
+    /// Also destroys every joint attached to the body
     pub fn destroy(handle: RigidBodyHandle) {
         let physics = super::physics();
+        for j in physics.joints.iter_index() {
+            let joint = physics.joints.get(&j).unwrap();
+            if joint.body_a == handle || joint.body_b == handle {
+                physics.joints.remove(&j);
+            }
+        }
+        physics.broadphase.remove(&handle);
         physics.bodies.remove(&handle);
     }
 
@@ -2,6 +2,8 @@ use crate::{V2, UpdateState};
 use crate::graphics::Graphics;
 use crate::utils::Rectangle;
 use crate::logger::PanicLogEntry;
+use crate::messages::{Message, MessageBus, Subscriber, Target};
+use std::sync::atomic::{AtomicU32, Ordering};
 
 mod label;
 mod labeled_value;
@@ -61,7 +63,12 @@ impl<'a> WidgetHelper<'a> {
     }
 }
 
+/// Identifies a widget for message subscriptions, unique for the lifetime of the game
+pub type WidgetId = u32;
+static NEXT_WIDGET_ID: AtomicU32 = AtomicU32::new(0);
+
 pub struct Widget {
+    id: WidgetId,
     bounds: Rectangle,
     h_alignment: HorizontalAlignment,
     v_alignment: VerticalAlignment,
@@ -74,6 +81,7 @@ pub struct Widget {
 impl Widget {
     pub fn new(behavior: impl UiElement + 'static, name: Option<&'static str>) -> Widget {
         Widget {
+            id: NEXT_WIDGET_ID.fetch_add(1, Ordering::Relaxed),
             bounds: Rectangle::new(V2::new(0., 0.), V2::new(0., 0.)),
             h_alignment: HorizontalAlignment::Center,
             v_alignment: VerticalAlignment::Center,
@@ -85,6 +93,10 @@ impl Widget {
         }
     }
 
+    pub fn id(&self) -> WidgetId {
+        self.id
+    }
+
     pub fn with_h_alignment(&mut self, alignment: HorizontalAlignment) -> &mut Self {
         self.h_alignment = alignment;
         self
@@ -143,21 +155,43 @@ impl Widget {
         None
     }
 
-    pub fn remove(&mut self, name: &'static str) {
-        let mut remove_index = None;
-        for (i, c) in self.children.iter_mut().enumerate() {
-            if let Some(n) = c.name && n == name {
-                remove_index = Some(i);
-                break;
-            }
-            c.remove(name);
+    pub fn find_widget_mut(&mut self, name: &'static str) -> Option<&mut Widget> {
+        if let Some(n) = self.name && name == n {
+            return Some(self);
+        }
+        self.children.iter_mut().find_map(|c| c.find_widget_mut(name))
+    }
+
+    pub fn find_id_mut(&mut self, id: WidgetId) -> Option<&mut Widget> {
+        if self.id == id {
+            return Some(self);
         }
+        self.children.iter_mut().find_map(|c| c.find_id_mut(id))
+    }
 
-        if let Some(i) = remove_index {
-            self.children.remove(i);
+    /// Removes the named widget and its subscriptions right away so a new widget can take its name
+    pub fn remove(&mut self, name: &'static str, messages: &mut MessageBus) {
+        if let Some(w) = self.take_named(name) {
+            let mut names = vec!();
+            w.unsubscribe(messages, &mut names);
+            self.cancel_unused_names(messages, names);
         }
     }
 
+    fn take_named(&mut self, name: &'static str) -> Option<Widget> {
+        for list in [&mut self.children, &mut self.pending_children] {
+            if let Some(i) = list.iter().position(|c| c.name == Some(name)) {
+                return Some(list.remove(i));
+            }
+        }
+        self.children.iter_mut().find_map(|c| c.take_named(name))
+    }
+
+    fn has_named(&self, name: &'static str) -> bool {
+        self.name == Some(name) ||
+        self.children.iter().chain(self.pending_children.iter()).any(|c| c.has_named(name))
+    }
+
     pub fn update(&mut self, state: &mut UpdateState) {
         for c in &mut self.children {
             c.update(state);
@@ -173,6 +207,17 @@ impl Widget {
         self.behavior.update(state, &mut helper, &self.bounds);
     }
 
+    pub fn process_message(&mut self, message: &Message) {
+        let mut helper = WidgetHelper {
+            children: &mut self.children,
+            pending_children: &mut self.pending_children,
+            mark_for_destroy: &mut self.mark_for_destroy,
+            h_alignment: &self.h_alignment,
+            v_alignment: &self.v_alignment,
+        };
+        self.behavior.process(message, &mut helper);
+    }
+
     pub fn render(&self, graphics: &mut Graphics, _rect: &Rectangle) {
         let rect = Rectangle::new(self.bounds.top_left(), self.bounds.size());
         self.behavior.render(graphics, &rect);
@@ -193,18 +238,59 @@ impl Widget {
         self.bounds = self.behavior.layout(rect, &mut helper);
     }
 
-    pub fn clear(&mut self) {
+    pub fn clear(&mut self, messages: &mut MessageBus) {
+        let mut names = vec!();
+        self.clear_destroyed(messages, &mut names);
+        self.cancel_unused_names(messages, names);
+    }
+
+    fn clear_destroyed(&mut self, messages: &mut MessageBus, names: &mut Vec<&'static str>) {
         if self.mark_for_destroy {
-            self.children.clear();
+            for c in self.children.drain(..) {
+                c.unsubscribe(messages, names);
+            }
             return;
         }
 
         for c in &mut self.children {
-            c.clear();
+            c.clear_destroyed(messages, names);
+            if c.mark_for_destroy {
+                c.unsubscribe(messages, names);
+            }
         }
         self.children.retain(|c| !c.mark_for_destroy);
     }
 
+    /// Removes the subscriptions and pending messages of this widget and all of its children
+    pub fn unregister_messages(&self, messages: &mut MessageBus) {
+        let mut names = vec!();
+        self.unsubscribe(messages, &mut names);
+        for name in names {
+            messages.cancel(&Target::Widget(name));
+        }
+    }
+
+    fn unsubscribe(&self, messages: &mut MessageBus, names: &mut Vec<&'static str>) {
+        messages.unsubscribe_all(&Subscriber::Widget(self.id));
+        names.extend(self.name);
+        for c in &self.children {
+            c.unsubscribe(messages, names);
+        }
+    }
+
+    /// Messages sent by name belong to whichever widget has that name, they are only cancelled once none are left
+    fn cancel_unused_names(&self, messages: &mut MessageBus, names: Vec<&'static str>) {
+        for name in names {
+            if !self.has_named(name) {
+                messages.cancel(&Target::Widget(name));
+            }
+        }
+    }
+
+    pub fn children(&self) -> &Vec<Widget> {
+        &self.children
+    }
+
     pub fn children_mut(&mut self) -> &mut Vec<Widget> {
         &mut self.children
     }
@@ -213,11 +299,12 @@ impl Widget {
         self.behavior.as_any_mut().downcast_mut::<T>().log_and_panic()
     }
 
-    pub fn create_widgets(&mut self) {
+    pub fn create_widgets(&mut self, messages: &mut MessageBus) {
         for c in self.children.iter_mut() {
-            c.create_widgets();
+            c.create_widgets(messages);
         }
         for w in self.pending_children.drain(..) {
+            w.behavior.register(messages, Subscriber::Widget(w.id));
             self.children.push(w);
         }
     }
@@ -235,6 +322,12 @@ pub trait UiElement {
     fn render(&self, graphics: &mut Graphics, bounds: &Rectangle);
     fn update(&mut self, state: &mut UpdateState, helper: &mut WidgetHelper, rect: &Rectangle);
     fn layout(&mut self, rect: &Rectangle, children: &mut WidgetHelper) -> Rectangle;
+
+    /// Called with messages sent to this widget's name or that it subscribed to in `register`
+    fn process(&mut self, _message: &Message, _helper: &mut WidgetHelper) { }
+    /// Subscribes to message types when the widget is created, subscriptions are removed when it is destroyed
+    /// `crate::handle_messages!` can be used to implement this
+    fn register(&self, _messages: &mut MessageBus, _subscriber: Subscriber) { }
 }
 
 pub struct Root { }
@@ -250,3 +343,71 @@ impl UiElement for Root {
         Rectangle::new(V2::new(0., 0.), rect.size())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::MessageContext;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    struct Ping;
+
+    struct Counter {
+        received: Rc<Cell<u32>>,
+    }
+    impl UiElement for Counter {
+        fn as_any(&self) -> &dyn std::any::Any { self }
+        fn as_any_mut(&mut self) -> &mut dyn std::any::Any { self }
+        fn render(&self, _graphics: &mut Graphics, _bounds: &Rectangle) {}
+        fn update(&mut self, _state: &mut UpdateState, _helper: &mut WidgetHelper, _rect: &Rectangle) {}
+        fn layout(&mut self, rect: &Rectangle, _helper: &mut WidgetHelper) -> Rectangle { rect.clone() }
+        fn process(&mut self, _message: &Message, _helper: &mut WidgetHelper) {
+            self.received.set(self.received.get() + 1);
+        }
+    }
+
+    fn deliver(root: &mut Widget, messages: &mut MessageBus) {
+        let mut entities = crate::entity::EntityManager::new();
+        messages.process_messages(&mut [], &mut MessageContext { root, entities: &mut entities });
+    }
+
+    #[test]
+    fn removed_widget_name_can_be_reused() {
+        let (old, new) = (Rc::new(Cell::new(0)), Rc::new(Cell::new(0)));
+        let mut messages = MessageBus::new();
+        let mut root = Widget::new(Root {}, None);
+        root.with_named_child(Counter { received: old.clone() }, "stats");
+        root.create_widgets(&mut messages);
+        messages.send_to(Target::Widget("stats"), Ping);
+
+        root.remove("stats", &mut messages);
+        root.with_named_child(Counter { received: new.clone() }, "stats");
+        root.create_widgets(&mut messages);
+        messages.send_to(Target::Widget("stats"), Ping);
+        deliver(&mut root, &mut messages);
+
+        assert_eq!(old.get(), 0);
+        assert_eq!(new.get(), 1);
+        assert_eq!(root.children().len(), 1);
+    }
+
+    #[test]
+    fn clearing_keeps_messages_for_a_widget_with_the_same_name() {
+        let (old, new) = (Rc::new(Cell::new(0)), Rc::new(Cell::new(0)));
+        let mut messages = MessageBus::new();
+        let mut root = Widget::new(Root {}, None);
+        root.with_named_child(Counter { received: old.clone() }, "stats");
+        root.create_widgets(&mut messages);
+
+        root.find_widget_mut("stats").unwrap().destroy();
+        root.with_named_child(Counter { received: new.clone() }, "stats");
+        messages.send_to(Target::Widget("stats"), Ping);
+        root.clear(&mut messages);
+        root.create_widgets(&mut messages);
+        deliver(&mut root, &mut messages);
+
+        assert_eq!(old.get(), 0);
+        assert_eq!(new.get(), 1);
+    }
+}
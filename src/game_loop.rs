@@ -9,7 +9,7 @@ use crate::V2;
 use crate::entity::{SceneBehavior};
 use crate::input::Actions;
 use crate::{input::Input, job_system::ThreadSafeJobQueue};
-use crate::messages::MessageBus;
+use crate::messages::{MessageBus, SharedMessageBus};
 use crate::graphics::Graphics;
 use crate::physics::QuadTree;
 use crate::utils::Rectangle;
@@ -40,13 +40,30 @@ static mut GLOBAL_STATE_VAR: Option<GlobalState> = None;
 pub fn global_state<'a>() -> &'a GlobalState {
     unsafe { GLOBAL_STATE_VAR.as_ref().unwrap() }
 }
+fn initialize_global_state(screen_size: V2) {
+    unsafe {
+        use std::borrow::BorrowMut;
+        *GLOBAL_STATE_VAR.borrow_mut() = Some(GlobalState::new(screen_size));
+    }
+}
+
+/// Frames that take longer than this are clamped so a long stall doesn't
+/// make the simulation try to catch up with hundreds of steps
+const MAX_FRAME_SECONDS: f32 = 0.25;
 
-pub fn start_game_loop<H>(title: &'static str,
-                          size: Option<(f32, f32)>,
-                          target_frames: u32,
+/// Window and timing options for `start_game_loop`
+pub struct GameLoopConfig {
+    pub title: &'static str,
+    /// Size of the window, fullscreen if `None`
+    pub size: Option<(f32, f32)>,
+    pub target_frames: u32,
+    pub target_updates: u32,
+}
+
+pub fn start_game_loop<H>(config: GameLoopConfig,
                           mut input: Input<Actions>,
                           queue: ThreadSafeJobQueue,
-                          scene: Box<dyn SceneBehavior>, 
+                          scene_behavior: Box<dyn SceneBehavior>, 
                           mut handler: H) -> ! 
     where H: WindowHandler + 'static {
     let el = glium::glutin::event_loop::EventLoop::new();
@@ -54,9 +71,9 @@ pub fn start_game_loop<H>(title: &'static str,
     //Build windows
     let monitor = el.primary_monitor();
     let builder = WindowBuilder::new()
-        .with_title(title)  
+        .with_title(config.title)  
         .with_visible(true);
-    let builder = match size {
+    let builder = match config.size {
         Some(s) => builder.with_inner_size(PhysicalSize::new(s.0, s.1)),
         None => builder.with_fullscreen(Some(Fullscreen::Borderless(monitor))),
     };
@@ -64,16 +81,15 @@ pub fn start_game_loop<H>(title: &'static str,
     let mut window = crate::graphics::create_window(&el, builder, queue.clone());
     let size = window.window_size();
 
-    let expected_seconds_per_frame = 1. / target_frames as f32;
+    let expected_seconds_per_frame = 1. / config.target_frames as f32;
+    let seconds_per_update = 1. / config.target_updates as f32;
 
     //Initialize game state
-    unsafe {
-        use std::borrow::BorrowMut;
-        *GLOBAL_STATE_VAR.borrow_mut() = Some(GlobalState::new(size));
-    }
+    initialize_global_state(size);
 
 
     let mut last_time = Instant::now();
+    let mut accumulator = 0.;
     let mut mouse_position = crate::V2::new(0., 0.);
 
     let message_bus = Rc::new(RefCell::new(MessageBus::new()));
@@ -84,8 +100,8 @@ pub fn start_game_loop<H>(title: &'static str,
     let mut quad_tree = QuadTree::new(bounds);
  
     //Start scene
-    let mut scene = crate::entity::Scene::new(scene);
-    scene.load(queue.clone(), message_bus.clone(), entities, &mut root);
+    let mut scene = crate::entity::Scene::new();
+    scene.load(scene_behavior, queue.clone(), message_bus.clone(), entities, &mut root);
     
     //Run game
     el.run(move |event, _, control_flow| {
@@ -120,37 +136,41 @@ pub fn start_game_loop<H>(title: &'static str,
 
             Event::MainEventsCleared => {
                 let now = Instant::now();
-                let delta_time = (now - last_time).as_millis() as f32 / 1000.;
+                let frame_time = (now - last_time).as_secs_f32();
                 last_time = now;
 
-                crate::input::gather(&mut input, mouse_position);
-
-                let mut state = crate::UpdateState::new(
-                    delta_time,
-                    &input,
-                    message_bus.clone(),
-                    queue.clone(),
-                    entities,
-                    &quad_tree,
-                );
-                if !handler.on_update(&mut state, &mut root, &mut scene) {
-                    *control_flow = ControlFlow::Exit;
-                    handler.on_stop();
-                }
+                // Gameplay and physics run in fixed steps, rendering happens once per frame
+                accumulator += f32::min(frame_time, MAX_FRAME_SECONDS);
+                while accumulator >= seconds_per_update {
+                    crate::input::gather(&mut input, mouse_position);
 
-                let mut messages = message_bus.borrow_mut();
-                unsafe { crate::physics::step_physics(expected_seconds_per_frame, &mut messages); }
+                    let mut state = crate::UpdateState::new(
+                        seconds_per_update,
+                        &input,
+                        message_bus.clone(),
+                        queue.clone(),
+                        entities,
+                        &quad_tree,
+                    );
+                    if !handler.on_update(&mut state, &mut root, &mut scene) {
+                        *control_flow = ControlFlow::Exit;
+                        handler.on_stop();
+                        break;
+                    }
+
+                    // The handler can scale time, physics and delayed messages have to use the same step as gameplay
+                    let delta_time = state.delta_time;
+                    end_update(delta_time, &message_bus, entities, &mut quad_tree, &mut root);
+                    accumulator -= seconds_per_update;
+                }
 
+                // How far we are between the last simulation step and the next one
+                window.set_interpolation(accumulator / seconds_per_update);
                 handler.on_render(&mut window, &root, &scene, entities);
                 window.draw_frame();
                 
                 // TODO skip on frame end if game is running slow
                 handler.on_frame_end();
-                
-                entities.dispose_entities(&mut messages);
-                quad_tree.update_positions(entities);
-                root.clear();
-                root.create_widgets();    
 
                 sleep_until_frame_end(now, expected_seconds_per_frame);
             },
@@ -159,6 +179,19 @@ pub fn start_game_loop<H>(title: &'static str,
     });
 }
 
+/// Everything that happens after the scene has been updated for a simulation step
+fn end_update(delta_time: f32, message_bus: &SharedMessageBus, entities: &mut EntityManager, quad_tree: &mut QuadTree, root: &mut Widget) {
+    let mut messages = message_bus.borrow_mut();
+    unsafe { crate::physics::step_physics(delta_time, &mut messages); }
+
+    entities.dispose_entities(&mut messages);
+    entities.update_transforms();
+    messages.advance(delta_time);
+    quad_tree.update_positions(entities);
+    root.clear(&mut messages);
+    root.create_widgets(&mut messages);
+}
+
 fn sleep_until_frame_end(start: Instant, expected_seconds_per_frame: f32) {
     let now = Instant::now();
 	let update_seconds = (now - start).as_secs_f32();
@@ -167,4 +200,229 @@ fn sleep_until_frame_end(start: Instant, expected_seconds_per_frame: f32) {
         let sleep_time = expected_seconds_per_frame - update_seconds;
         std::thread::sleep(std::time::Duration::from_secs_f32(sleep_time));
 	}
-}
\ No newline at end of file
+}
+
+/// Runs a scene without a window, graphics context or OS input
+/// Each call to `step` performs a single fixed update, input can be changed between steps with `input_mut`
+/// Entities and physics bodies live in the global singletons so only one should be running at a time
+pub struct HeadlessGameLoop {
+    input: Input<Actions>,
+    delta_time: f32,
+    time_scale: f32,
+    queue: ThreadSafeJobQueue,
+    message_bus: SharedMessageBus,
+    quad_tree: QuadTree,
+    root: Widget,
+    scene: Scene,
+    frame: u32,
+}
+impl HeadlessGameLoop {
+    pub fn new(screen_size: V2,
+               delta_time: f32,
+               input: Input<Actions>,
+               queue: ThreadSafeJobQueue,
+               scene_behavior: Box<dyn SceneBehavior>) -> HeadlessGameLoop {
+        initialize_global_state(screen_size);
+
+        let message_bus = Rc::new(RefCell::new(MessageBus::new()));
+        let entities = crate::entity::entity_manager();
+        let mut root = Widget::new(Root {}, None);
+        let quad_tree = QuadTree::new(Rectangle::new(V2::new(0., 0.), screen_size));
+
+        let mut scene = Scene::new();
+        scene.load(scene_behavior, queue.clone(), message_bus.clone(), entities, &mut root);
+        root.create_widgets(&mut message_bus.borrow_mut());
+
+        HeadlessGameLoop { input, delta_time, time_scale: 1., queue, message_bus, quad_tree, root, scene, frame: 0 }
+    }
+
+    /// Runs a single update, returns false once the scene has unloaded
+    pub fn step(&mut self) -> bool {
+        let entities = crate::entity::entity_manager();
+        let delta_time = self.delta_time * self.time_scale;
+        let mut state = crate::UpdateState::new(
+            delta_time,
+            &self.input,
+            self.message_bus.clone(),
+            self.queue.clone(),
+            entities,
+            &self.quad_tree,
+        );
+        if !self.scene.update(&mut state, &mut self.root) {
+            return false;
+        }
+
+        end_update(delta_time, &self.message_bus, entities, &mut self.quad_tree, &mut self.root);
+        // Done after the update so the next step sees this one's input as the previous frame
+        crate::input::advance_frame(&mut self.input);
+        self.frame += 1;
+        true
+    }
+
+    /// Speeds up or slows down every following step the same way the game's speed controls do
+    #[allow(dead_code)]
+    pub fn set_time_scale(&mut self, scale: f32) {
+        self.time_scale = scale;
+    }
+
+    /// Runs up to `frames` updates, stopping early if the scene unloads
+    pub fn run(&mut self, frames: u32) -> bool {
+        for _ in 0..frames {
+            if !self.step() {
+                return false;
+            }
+        }
+        true
+    }
+
+    pub fn entities(&self) -> &EntityManager { crate::entity::entity_manager() }
+    pub fn input_mut(&mut self) -> &mut Input<Actions> { &mut self.input }
+    pub fn is_loading(&self) -> bool { self.scene.is_loading() }
+    pub fn frame(&self) -> u32 { self.frame }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use crate::messages::{Message, MessageHandler, MessageContext, Target};
+    use crate::entity::SceneLoad;
+
+    /// Entities, physics and assets are global so headless loops can't run in parallel
+    static HEADLESS: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    fn headless(scene: impl SceneBehavior + 'static) -> HeadlessGameLoop {
+        static ASSETS: std::sync::Once = std::sync::Once::new();
+        ASSETS.call_once(crate::assets::initialize_asset_cache);
+
+        let (queue, _) = crate::job_system::start_job_system();
+        let queue = std::sync::Arc::new(std::sync::Mutex::new(RefCell::new(queue)));
+        let mut input = Input::new();
+        crate::input::load_input_settings(&mut input, &crate::settings::SettingsFile::default());
+        HeadlessGameLoop::new(V2::new(640., 480.), 1. / 60., input, queue, Box::new(scene))
+    }
+
+    struct Ping;
+
+    /// Sends itself a delayed message and records the frame it arrives on
+    struct DelayedPing {
+        sent: bool,
+        frame: u32,
+        received: Rc<Cell<Option<u32>>>,
+    }
+    impl SceneBehavior for DelayedPing {
+        fn load(&mut self, _queue: ThreadSafeJobQueue, _entities: &mut EntityManager, _root: &mut Widget) { }
+        fn unload(&mut self) { }
+        fn update(&mut self, state: &mut crate::UpdateState, _root: &mut Widget) -> SceneLoad {
+            if !self.sent {
+                state.send_message_delayed(Target::All, Ping, 1.);
+                self.sent = true;
+            }
+            self.frame += 1;
+            SceneLoad::None
+        }
+        fn render(&self, _graphics: &mut Graphics) { }
+    }
+    impl MessageHandler for DelayedPing {
+        crate::handle_messages!(Ping);
+        fn process(&mut self, message: &Message, _context: &mut MessageContext) {
+            if message.is::<Ping>() {
+                self.received.set(Some(self.frame));
+            }
+        }
+    }
+
+    /// Needs an asset to load before it accepts input, then loads the next scene on Accept
+    struct WaitForAccept {
+        next_loaded: Rc<Cell<bool>>,
+    }
+    impl SceneBehavior for WaitForAccept {
+        fn assets(&self) -> crate::assets::AssetManifest {
+            crate::assets::AssetManifest::new().data(crate::assets::DataFiles::Levels)
+        }
+        fn load(&mut self, _queue: ThreadSafeJobQueue, _entities: &mut EntityManager, _root: &mut Widget) { }
+        fn unload(&mut self) { }
+        fn update(&mut self, state: &mut crate::UpdateState, _root: &mut Widget) -> SceneLoad {
+            if state.action_pressed(Actions::Accept) {
+                return SceneLoad::Load(Box::new(NextScene { loaded: self.next_loaded.clone() }));
+            }
+            SceneLoad::None
+        }
+        fn render(&self, _graphics: &mut Graphics) { }
+    }
+    impl MessageHandler for WaitForAccept {
+        crate::handle_messages!();
+        fn process(&mut self, _message: &Message, _context: &mut MessageContext) { }
+    }
+
+    struct NextScene {
+        loaded: Rc<Cell<bool>>,
+    }
+    impl SceneBehavior for NextScene {
+        fn load(&mut self, _queue: ThreadSafeJobQueue, _entities: &mut EntityManager, _root: &mut Widget) {
+            self.loaded.set(true);
+        }
+        fn unload(&mut self) { }
+        fn update(&mut self, _state: &mut crate::UpdateState, _root: &mut Widget) -> SceneLoad { SceneLoad::None }
+        fn render(&self, _graphics: &mut Graphics) { }
+    }
+    impl MessageHandler for NextScene {
+        crate::handle_messages!();
+        fn process(&mut self, _message: &Message, _context: &mut MessageContext) { }
+    }
+
+    #[test]
+    fn input_changes_scene_after_loading() {
+        let _lock = HEADLESS.lock().unwrap_or_else(|e| e.into_inner());
+        let next_loaded = Rc::new(Cell::new(false));
+        let mut game = headless(WaitForAccept { next_loaded: next_loaded.clone() });
+
+        let start = Instant::now();
+        while game.is_loading() {
+            assert!(start.elapsed().as_secs() < 5, "scene assets never finished loading");
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            game.step();
+        }
+        game.step();
+        assert!(!next_loaded.get());
+
+        game.input_mut().set_action(Actions::Accept, true);
+        game.step();
+        assert!(next_loaded.get());
+    }
+
+    struct UnloadImmediately;
+    impl SceneBehavior for UnloadImmediately {
+        fn load(&mut self, _queue: ThreadSafeJobQueue, _entities: &mut EntityManager, _root: &mut Widget) { }
+        fn unload(&mut self) { }
+        fn update(&mut self, _state: &mut crate::UpdateState, _root: &mut Widget) -> SceneLoad { SceneLoad::Unload }
+        fn render(&self, _graphics: &mut Graphics) { }
+    }
+    impl MessageHandler for UnloadImmediately {
+        crate::handle_messages!();
+        fn process(&mut self, _message: &Message, _context: &mut MessageContext) { }
+    }
+
+    #[test]
+    fn stepping_after_unload_does_nothing() {
+        let _lock = HEADLESS.lock().unwrap_or_else(|e| e.into_inner());
+        let mut game = headless(UnloadImmediately);
+        assert!(!game.step());
+        assert!(!game.step());
+        assert_eq!(game.frame(), 0);
+    }
+
+    #[test]
+    fn time_scale_applies_to_delayed_messages() {
+        let _lock = HEADLESS.lock().unwrap_or_else(|e| e.into_inner());
+        let received = Rc::new(Cell::new(None));
+        let mut game = headless(DelayedPing { sent: false, frame: 0, received: received.clone() });
+        game.set_time_scale(0.5);
+
+        game.run(100);
+        assert_eq!(received.get(), None, "message arrived before a second of scaled time");
+        game.run(30);
+        let frame = received.get().expect("message never arrived");
+        assert!((119..=122).contains(&frame), "arrived on frame {}", frame);
+    }
+}
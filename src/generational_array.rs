@@ -1,81 +1,115 @@
 use std::collections::VecDeque;
-use crate::utils::init_optional_array_to_blank;
 
-#[derive(Clone, Copy, Default, Eq, PartialEq, Hash)]
+#[derive(Clone, Copy, Default, Eq, PartialEq, Hash, Debug)]
 pub struct GenerationalIndex {
     pub index: usize,
     version: u16,
 }
 
+/// Arena that hands out stable indices to its values
+/// Values are stored in chunks of `C` elements that are allocated as needed,
+/// a chunk is never moved once allocated so pointers into the array stay valid while it grows
 pub struct GenerationalArray<T: Sized, const C: usize> {
-    data: [Option<T>; C],
-    version: [u16; C],
-    allocated: [u16; C],
+    chunks: Vec<Box<[Option<T>]>>,
+    version: Vec<u16>,
+    allocated: Vec<u16>,
     free: VecDeque<usize>,
-    last_index: usize
+    last_index: usize,
+    count: usize,
+    /// Number of values after which `on_limit` is called, this is not enforced
+    soft_limit: Option<usize>,
+    on_limit: Option<fn(usize)>,
 }
 
 impl<T: Sized, const C: usize> GenerationalArray<T, C> {
     pub fn new() -> GenerationalArray<T, C> {
         GenerationalArray {
-            data: init_optional_array_to_blank::<T, C>(),
-            version: [0; C],
-            allocated: [0; C],
+            chunks: vec!(),
+            version: vec!(),
+            allocated: vec!(),
             free: VecDeque::new(),
-            last_index: 0
+            last_index: 0,
+            count: 0,
+            soft_limit: None,
+            on_limit: None,
         }
     }
 
+    /// Creates an array that calls `on_limit` every time a push takes it past `limit` values
+    pub fn with_soft_limit(limit: usize, on_limit: fn(usize)) -> GenerationalArray<T, C> {
+        let mut array = Self::new();
+        array.soft_limit = Some(limit);
+        array.on_limit = Some(on_limit);
+        array
+    }
+
     pub fn len(&self) -> usize {
         self.last_index
     }
 
+    /// Number of values currently in the array
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
     pub fn get_at(&self, index: usize) -> Option<&T> {
-        self.data[index].as_ref()
+        self.chunks.get(index / C)?[index % C].as_ref()
     }
     pub fn get_at_mut(&mut self, index: usize) -> Option<&mut T> {
-        unsafe { self.data.get_unchecked_mut(index).as_mut() }
+        self.chunks.get_mut(index / C)?[index % C].as_mut()
     }
 
     pub fn get(&self, index: &GenerationalIndex) -> Option<&T> {
-        let version = self.version[index.index];
-        if version == index.version {
-            return self.data[index.index].as_ref()
+        if self.version.get(index.index) == Some(&index.version) {
+            return self.get_at(index.index)
         }
         None
     }
 
     pub fn get_mut(&mut self, index: &GenerationalIndex) -> Option<&mut T> {
-        let version = self.version[index.index];
-        if version == index.version {
-            return self.data[index.index].as_mut()
+        if self.version.get(index.index) == Some(&index.version) {
+            return self.get_at_mut(index.index)
         }
         None
     }
 
     pub fn push(&mut self, value: T) -> (GenerationalIndex, &mut T) {
+        if let Some(limit) = self.soft_limit && self.count == limit {
+            (self.on_limit.unwrap())(limit);
+        }
+
         let index = match self.free.pop_front() {
             Some(i) => i,
             None => {
                 self.last_index += 1;
+                self.grow(self.last_index);
                 self.last_index - 1
             }
         };
 
-        //TODO handle full
-        self.data[index] = Some(value);
+        self.count += 1;
         self.allocated[index] = self.version[index];
-        
-        let data = self.data[index].as_mut().unwrap();
+
+        let data = &mut self.chunks[index / C][index % C];
+        *data = Some(value);
         let index = GenerationalIndex { index, version: self.version[index] };
-        (index, data)
+        (index, data.as_mut().unwrap())
+    }
+
+    fn grow(&mut self, len: usize) {
+        while self.chunks.len() * C < len {
+            self.chunks.push((0..C).map(|_| None).collect());
+        }
+        self.version.resize(len, 0);
+        self.allocated.resize(len, 0);
     }
 
     pub fn remove(&mut self, index: &GenerationalIndex) -> bool {
-        if index.version == self.version[index.index] {
+        if self.version.get(index.index) == Some(&index.version) {
 
-            self.data[index.index] = None;
-            self.version[index.index] += 1;
+            self.chunks[index.index / C][index.index % C] = None;
+            self.version[index.index] = self.version[index.index].wrapping_add(1);
+            self.count -= 1;
 
             self.free.push_back(index.index);
             return true;
@@ -127,8 +161,8 @@ impl<'a, T: Sized, const C: usize> Iterator for Iter<'a, T, C> {
                 return None;
             }
         }
-        let data = self.array.data[self.current].as_ref();
+        let data = self.array.get_at(self.current);
         self.current += 1;
         data
     }
-}
\ No newline at end of file
+}
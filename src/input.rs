@@ -202,6 +202,12 @@ impl<T: Eq + Hash> Input<T> {
         let key = self.map.get(&action).expect("All actions should be in input map");
         Self::consume_input(&self.consumed_input, *key)
     }
+    pub fn consume_all(&self) {
+        let mut input = self.consumed_input.borrow_mut();
+        for i in input.iter_mut() {
+            *i = u64::MAX;
+        }
+    }
     pub fn action_down(&self, action: T) -> bool {
         let key = self.map.get(&action).expect("All actions should be in input map");
         Self::key_down(&self.input, &self.consumed_input, *key)
@@ -223,18 +229,23 @@ impl<T: Eq + Hash> Input<T> {
     pub fn mouse_pos(&self) -> V2 {
         self.mouse_position
     }
+
+    /// Sets the state of the key mapped to an action directly instead of reading it from the OS
+    pub fn set_action(&mut self, action: T, down: bool) {
+        let key = self.map.get(&action).expect("All actions should be in input map");
+        self.input[*key as usize] = if down { 0x80 } else { 0 };
+    }
+    pub fn set_mouse_pos(&mut self, position: V2) {
+        self.mouse_position = position;
+    }
 }
 impl<T: Eq + Hash> Default for Input<T> {
     fn default() -> Self { Self::new() }
 }
 
 pub fn gather(input: &mut Input<Actions>, position: V2) {
-    input.previous_input = input.input;
     input.mouse_position = position;
-    let mut consumed = input.consumed_input.borrow_mut();
-    for i in consumed.iter_mut() {
-        *i = 0;
-    }
+    advance_frame(input);
 
     #[cfg(target_os = "windows")]
     {
@@ -243,6 +254,16 @@ pub fn gather(input: &mut Input<Actions>, position: V2) {
     }
 }
 
+/// Moves the current input to the previous frame without polling the OS
+/// Used directly when input is being driven synthetically
+pub fn advance_frame(input: &mut Input<Actions>) {
+    input.previous_input = input.input;
+    let mut consumed = input.consumed_input.borrow_mut();
+    for i in consumed.iter_mut() {
+        *i = 0;
+    }
+}
+
 pub fn load_input_settings(input: &mut Input<Actions>, settings: &SettingsFile) {
     fn add_action_from_settings(input: &mut Input<Actions>, settings: &SettingsFile, setting: SettingNames, action: Actions) {
         let setting = settings.get_str(setting);
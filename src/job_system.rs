@@ -33,6 +33,7 @@ pub enum JobType {
     LoadImage(&'static str),
     LoadFont(&'static str),
     LoadSound(&'static str),
+    LoadData(&'static str),
 }
 
 pub type ThreadSafeJobQueue = Arc<std::sync::Mutex<RefCell<JobQueue>>>;
@@ -78,6 +79,7 @@ fn poll_pending_jobs(queue: spmc::Receiver<Job>, notify: std::sync::mpsc::Sender
             JobType::LoadImage(path) => crate::assets::load_image_async(path, state.unwrap()),
             JobType::LoadFont(path) => crate::assets::load_font_async(path, state.unwrap()),
             JobType::LoadSound(path) => crate::assets::load_sound_async(path, state.unwrap()),
+            JobType::LoadData(path) => crate::assets::load_data_async(path, state.unwrap()),
         }
 
         notify.send(0).log("Unable to notify main loop about finished job");
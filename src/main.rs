@@ -9,6 +9,7 @@ use logger::LogEntry;
 use entity::{Scene, EntityManager};
 use input::Actions;
 use graphics::Graphics;
+use messages::{SharedMessageBus, MessageTracer};
 
 mod assets;
 mod job_system;
@@ -29,25 +30,52 @@ pub use update_state::UpdateState;
 
 
 /* TODO
- * Scene transitions
  * RigidBodies should be offset by half scale
- * Have UI elements handle messages
  */
 
+const MESSAGE_TRACE_CAPACITY: usize = 256;
+
 struct GameState {
     settings: settings::SettingsFile,
     delta_time_scale: f32,
     audio: std::sync::mpsc::Sender<()>,
     is_playing: bool,
+    message_bus: Option<SharedMessageBus>,
+    message_trace: String,
+}
+impl GameState {
+    /// Turns message tracing on or off when the setting changes
+    fn update_message_trace(&mut self, message_bus: &SharedMessageBus) {
+        let trace = self.settings.get_str(settings::SettingNames::MessageTrace);
+        if trace == self.message_trace { return; }
+
+        let tracer = MessageTracer::new(MESSAGE_TRACE_CAPACITY);
+        let tracer = match trace.as_str() {
+            "Off" => None,
+            "Memory" => Some(tracer),
+            "Log" => Some(tracer.with_log()),
+            path => tracer.with_file(path).map(Some).log("Unable to open message trace file"),
+        };
+        message_bus.borrow_mut().set_tracer(tracer);
+        self.message_trace = trace;
+    }
 }
 
 impl game_loop::WindowHandler for GameState {
     fn on_render(&mut self, graphics: &mut Graphics, root: &crate::ui::Widget, scene: &Scene, entities: &EntityManager) {
         scene.render(graphics, root, entities);
+
+        let lines = self.settings.get_i32(settings::SettingNames::MessageTraceOverlay);
+        if lines > 0 && let Some(bus) = &self.message_bus && let Some(tracer) = bus.borrow().tracer() {
+            tracer.render_overlay(graphics, lines as usize);
+        }
     }
 
     fn on_update(&mut self, state: &mut UpdateState, root: &mut crate::ui::Widget, scene: &mut Scene) -> bool {
         settings::update_settings(&mut self.settings).log("Unable to load new settings");
+        state.entities.prefabs().update().log("Unable to load new prefabs");
+        self.update_message_trace(&state.message_bus);
+        self.message_bus.get_or_insert_with(|| state.message_bus.clone());
         state.delta_time *= self.delta_time_scale;
         
         self.is_playing = scene.update(state, root);
@@ -81,9 +109,28 @@ fn main() {
     };
     logger::set_log_level(&settings.get_str(settings::SettingNames::LogLevel));
 
+    // Layers have to be registered before the prefabs that use them are loaded
+    let prefabs = entity::entity_manager().prefabs();
+    gust::register_prefabs(prefabs);
+    prefabs.load("./resources/prefabs.ron").log("Unable to load prefabs");
+
     let mut input = input::Input::new();
     crate::input::load_input_settings(&mut input, &settings);
 
+    // `--headless <frames>` steps the game without a window or audio as a smoke test
+    if let Some(frames) = headless_frames() {
+        let mut game = game_loop::HeadlessGameLoop::new(V2::new(640., 480.), 1. / 60., input, q,
+                                                        Box::new(gust::main_menu::MainMenu::new()));
+        // Wait for the menu to load, then start a game so a level gets exercised too
+        while game.is_loading() && game.frame() < frames && game.step() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        game.input_mut().set_action(Actions::Accept, true);
+        game.run(frames.saturating_sub(game.frame()));
+        logger::info!("Headless run finished after {} frames with {} entities", game.frame(), game.entities().iter_handles().len());
+        return;
+    }
+
     let audio = assets::start_audio_engine();
 
     let state = GameState {
@@ -91,9 +138,21 @@ fn main() {
         settings, 
         audio,
         is_playing: true,
+        message_bus: None,
+        message_trace: String::from("Off"),
+    };
+    let config = game_loop::GameLoopConfig {
+        title: "gust",
+        size: Some((640., 480.)),
+        target_frames: 60,
+        target_updates: 60,
     };
-    game_loop::start_game_loop("gust", Some((640., 480.)),
-                               60, input, q,
+    game_loop::start_game_loop(config, input, q,
                                Box::new(gust::main_menu::MainMenu::new()),
                                state)
-}
\ No newline at end of file
+}
+
+fn headless_frames() -> Option<u32> {
+    let mut args = std::env::args().skip_while(|a| a != "--headless").skip(1);
+    args.next().map(|frames| frames.parse().expect("--headless expects a frame count"))
+}
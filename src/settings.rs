@@ -71,6 +71,11 @@ settings_enum! {
         ActionCancel("action_cancel") = SettingValue::String(String::from("Escape")),
         
         AssetCacheSizeMb("asset_cache_size_mb") = SettingValue::I32(32),
+
+        // Off, Memory, Log or a file path to write every message to
+        MessageTrace("message_trace") = SettingValue::String(String::from("Off")),
+        // Number of traced messages to show on screen
+        MessageTraceOverlay("message_trace_overlay") = SettingValue::I32(0),
     }
 }
 
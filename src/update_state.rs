@@ -1,8 +1,8 @@
 use crate::V2;
 use crate::input::{Input, Actions};
-use crate::messages::{SharedMessageBus, Messages};
+use crate::messages::{SharedMessageBus, Target};
 use crate::job_system::ThreadSafeJobQueue;
-use crate::entity::EntityManager;
+use crate::entity::{EntityManager, EntityCommands};
 use crate::physics::QuadTree;
 
 pub struct UpdateState<'a> {
@@ -22,9 +22,21 @@ impl<'a> UpdateState<'a> {
                quad_tree: &'a QuadTree) -> UpdateState<'a> {
         UpdateState { delta_time, input, message_bus, queue, entities, quad_tree }
     }
-    pub fn send_message(&self, kind: Messages) {
+    /// Entity changes that are applied once every entity has updated
+    pub fn commands(&mut self) -> &mut EntityCommands {
+        self.entities.defer()
+    }
+    pub fn send_message<T: 'static>(&self, message: T) {
+        let mut m = self.message_bus.borrow_mut();
+        m.send(message);
+    }
+    pub fn send_message_to<T: 'static>(&self, target: Target, message: T) {
         let mut m = self.message_bus.borrow_mut();
-        m.send(kind);
+        m.send_to(target, message);
+    }
+    pub fn send_message_delayed<T: 'static>(&self, target: Target, message: T, seconds: f32) {
+        let mut m = self.message_bus.borrow_mut();
+        m.send_delayed(target, message, seconds);
     }
     pub fn action_down(&self, action: Actions) -> bool {
        self.input.action_down(action)
@@ -44,4 +56,7 @@ impl<'a> UpdateState<'a> {
     pub fn consume_action(&self, action: Actions) {
         self.input.consume_action(action)
     }
+    pub fn consume_all_actions(&self) {
+        self.input.consume_all()
+    }
 }